@@ -1,5 +1,6 @@
 use blobwar::board::Board;
 use blobwar::configuration::Configuration;
+use blobwar::notation::Fen;
 fn main() {
     let board: Board = Default::default();
     let start_state = Configuration::new(&board);
@@ -9,4 +10,9 @@ fn main() {
     let deserialized_board = Board::deserialize(&string);
     let deserialized_configuration = Configuration::deserialize(&string, &deserialized_board);
     println!("deserialized: {}", deserialized_configuration);
+    let position = Fen::from_configuration(&start_state, 0);
+    println!("position: {}", position);
+    let parsed: Fen = position.to_string().parse().expect("invalid position");
+    let parsed_board = parsed.board();
+    println!("parsed: {}", parsed.configuration(&parsed_board));
 }
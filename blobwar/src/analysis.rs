@@ -0,0 +1,366 @@
+//! Analysis of arbitrary positions : score every legal move with its principal variation.
+//!
+//! Analysis is done by the search engines of the `Registry` themselves, by iterative deepening
+//! until a maximum depth or a time limit. At depth `d` the principal variation of every root move
+//! is the line the engine plays from there, searching `d - 1` plies deep for the first reply and
+//! one less for each next move ; its score is the blob difference at the end of the line, from
+//! the point of view of the side to move.
+//!
+//! Searches run on a thread pool of their own, so that the nodes they visit (the configurations
+//! they play) can be counted. The time limit (and the stop flag if any) is checked before each
+//! search of a line, an interrupted iteration is dropped.
+use std::cmp::Reverse;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::configuration::{format_move, take_played, Configuration, Movement};
+use crate::strategy::registry::DEFAULT_DEPTH;
+use crate::strategy::{EngineSpec, Registry};
+
+/// Maximum depth for timed analysis.
+const MAX_DEPTH: u8 = 64;
+
+/// Moves played by the engine and the position they lead to.
+type Line<'a> = (Vec<Option<Movement>>, Configuration<'a>);
+
+#[derive(Clone, Debug)]
+/// Analysis of one root move.
+pub struct MoveAnalysis {
+    /// The move (`None` for a pass).
+    pub movement: Option<Movement>,
+    /// Score for the side to move after the principal variation.
+    pub score: i32,
+    /// Expected continuation, starting with `movement`.
+    pub pv: Vec<Option<Movement>>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Analysis of a position at a given depth.
+pub struct Analysis {
+    /// All root moves, best first.
+    pub moves: Vec<MoveAnalysis>,
+    /// Depth of the last completed iteration.
+    pub depth: u8,
+    /// Nodes searched so far.
+    pub nodes: u64,
+    /// Time spent.
+    pub time: Duration,
+    /// How many principal variations to display (all if `None`).
+    pub multi_pv: Option<usize>,
+}
+
+impl Analysis {
+    /// Best move if any.
+    pub fn best(&self) -> Option<&MoveAnalysis> {
+        self.moves.first()
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Analysis settings.
+pub struct Analyzer {
+    /// Engine, without depth nor time.
+    engine: EngineSpec,
+    /// Maximum depth.
+    pub depth: u8,
+    /// Time limit.
+    pub time: Option<Duration>,
+    /// Give up once set.
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl Analyzer {
+    /// Settings corresponding to given engine spec, which must be a search engine.
+    pub fn from_spec(spec: &EngineSpec) -> Result<Self, String> {
+        let registry = Registry::default();
+        if registry.anytime(&spec.strategy).is_none() {
+            return Err(format!("{} cannot analyze positions", spec.strategy));
+        }
+        let engine = EngineSpec {
+            depth: None,
+            time: None,
+            name: None,
+            ..spec.clone()
+        };
+        // refuse invalid options right away
+        registry.build(&engine)?;
+        Ok(Analyzer {
+            engine,
+            depth: spec.depth.unwrap_or(if spec.time.is_some() {
+                MAX_DEPTH
+            } else {
+                DEFAULT_DEPTH
+            }),
+            time: spec.time.map(Duration::from_millis),
+            stop: None,
+        })
+    }
+
+    /// Stop analyzing as soon as given flag is set.
+    pub fn stop_flag(self, stop: Arc<AtomicBool>) -> Self {
+        Analyzer {
+            stop: Some(stop),
+            ..self
+        }
+    }
+
+    /// Should we give up ?
+    fn interrupted(&self, deadline: Option<Instant>) -> bool {
+        deadline.is_some_and(|d| Instant::now() >= d)
+            || self
+                .stop
+                .as_ref()
+                .is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// Line played by the engine from given position, searching `depth` plies deep for the first
+    /// move and one less for each next move. Return it with the position at its end, `None` if
+    /// interrupted before it was complete.
+    fn line<'a>(
+        &self,
+        searcher: &mut Searcher,
+        state: &Configuration<'a>,
+        depth: u8,
+        mut passed: bool,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Line<'a>>, String> {
+        let mut state = *state;
+        let mut line = Vec::new();
+        for depth in (1..=depth).rev() {
+            if state.game_over() {
+                break;
+            }
+            if self.interrupted(deadline) {
+                return Ok(None);
+            }
+            let spec = EngineSpec {
+                depth: Some(depth),
+                ..self.engine.clone()
+            };
+            let movement = searcher.search(&spec, &state)?;
+            match movement {
+                Some(ref movement) if state.check_move(movement) => state.apply_movement(movement),
+                Some(_) => return Err(format!("{} played an illegal move", self.engine)),
+                // nobody can move anymore
+                None if passed => break,
+                None => state = state.skip_play(),
+            }
+            passed = movement.is_none();
+            line.push(movement);
+        }
+        Ok(Some((line, state)))
+    }
+
+    /// Analyze given position, calling `progress` after each completed depth.
+    pub fn analyze_with<F: FnMut(&Analysis)>(
+        &self,
+        state: &Configuration,
+        mut progress: F,
+    ) -> Result<Analysis, String> {
+        let start = Instant::now();
+        let deadline = self.time.map(|t| start + t);
+        let mut searcher = Searcher::new();
+        let mut root_moves: Vec<Option<Movement>> = state.movements().map(Some).collect();
+        if root_moves.is_empty() && !state.game_over() {
+            root_moves.push(None);
+        }
+        let mut analysis = Analysis::default();
+        if state.game_over() {
+            return Ok(analysis);
+        }
+        'deepening: for depth in 1..=self.depth {
+            let mut moves = Vec::new();
+            for &movement in &root_moves {
+                let child = movement.map_or_else(|| state.skip_play(), |m| state.play(&m));
+                searcher.nodes += 1;
+                // the first iteration searches nothing and always completes
+                let (line, end) = match self.line(
+                    &mut searcher,
+                    &child,
+                    depth - 1,
+                    movement.is_none(),
+                    deadline,
+                )? {
+                    Some(line) => line,
+                    None => break 'deepening,
+                };
+                let red_value = end.red_value() as i32;
+                moves.push(MoveAnalysis {
+                    movement,
+                    score: if state.current_player {
+                        -red_value
+                    } else {
+                        red_value
+                    },
+                    pv: std::iter::once(movement).chain(line).collect(),
+                });
+            }
+            moves.sort_by_key(|m| Reverse(m.score));
+            // search best moves first on next iteration
+            root_moves = moves.iter().map(|m| m.movement).collect();
+            analysis.moves = moves;
+            analysis.depth = depth;
+            analysis.nodes = searcher.nodes;
+            analysis.time = start.elapsed();
+            progress(&analysis);
+        }
+        analysis.nodes = searcher.nodes;
+        analysis.time = start.elapsed();
+        Ok(analysis)
+    }
+
+    /// Analyze given position.
+    pub fn analyze(&self, state: &Configuration) -> Result<Analysis, String> {
+        self.analyze_with(state, |_| {})
+    }
+}
+
+/// Runs the searches of an analysis and counts their nodes.
+struct Searcher {
+    registry: Registry,
+    /// Threads of the searches (parallel engines included), none if threads are not available.
+    pool: Option<ThreadPool>,
+    /// Nodes searched so far, root moves included.
+    nodes: u64,
+}
+
+impl Searcher {
+    fn new() -> Self {
+        Searcher {
+            registry: Registry::default(),
+            pool: ThreadPoolBuilder::new().build().ok(),
+            nodes: 0,
+        }
+    }
+
+    /// Move chosen by the engine of given spec.
+    fn search(
+        &mut self,
+        spec: &EngineSpec,
+        state: &Configuration,
+    ) -> Result<Option<Movement>, String> {
+        let registry = &self.registry;
+        let search = || {
+            registry
+                .build(spec)
+                .map(|mut engine| engine.compute_next_move(state))
+        };
+        match self.pool {
+            Some(ref pool) => {
+                let movement = pool.install(search);
+                self.nodes += pool.broadcast(|_| take_played()).into_iter().sum::<u64>();
+                movement
+            }
+            None => {
+                take_played();
+                let movement = search();
+                self.nodes += take_played();
+                movement
+            }
+        }
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "depth {} nodes {} time {}ms",
+            self.depth,
+            self.nodes,
+            self.time.as_millis()
+        )?;
+        let shown = self.multi_pv.unwrap_or(self.moves.len());
+        for (rank, analysis) in self.moves.iter().take(shown).enumerate() {
+            writeln!(
+                f,
+                "{:>3}. {:<6} {:>+4}  pv {}",
+                rank + 1,
+                format_move(&analysis.movement),
+                analysis.score,
+                analysis
+                    .pv
+                    .iter()
+                    .map(format_move)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::Fen;
+
+    /// Red duplicating on b2 takes five blue blobs, no other move takes more than two.
+    const CAPTURE: &str = "r1b5/2b5/bbb5/8/8/8/8/7b r 0";
+
+    fn analyze(spec: &str, position: &str) -> Analysis {
+        let position: Fen = position.parse().unwrap();
+        let board = position.board();
+        Analyzer::from_spec(&spec.parse().unwrap())
+            .unwrap()
+            .analyze(&position.configuration(&board))
+            .unwrap()
+    }
+
+    #[test]
+    fn captures_come_first() {
+        for spec in &["minmax:depth=1", "minmax:depth=3", "alphabeta:depth=3"] {
+            let analysis = analyze(spec, CAPTURE);
+            assert_eq!(analysis.moves.len(), 3, "{}", spec);
+            let best = analysis.best().unwrap();
+            assert_eq!(best.movement, Some("b2".parse().unwrap()), "{}", spec);
+            assert!(analysis.moves[1].score < best.score, "{}", spec);
+        }
+        let shallow = analyze("minmax:depth=1", CAPTURE);
+        assert_eq!(shallow.best().unwrap().score, 6);
+        assert_eq!(shallow.best().unwrap().pv.len(), 1);
+    }
+
+    #[test]
+    fn depth_and_nodes() {
+        let one = analyze("alphabeta:depth=1", CAPTURE);
+        assert_eq!(one.depth, 1);
+        assert_eq!(one.nodes, 3);
+        let three = analyze("alphabeta:depth=3", CAPTURE);
+        assert_eq!(three.depth, 3);
+        assert!(three.nodes > 3 * one.nodes);
+        assert!(three.best().unwrap().pv.len() <= 3);
+    }
+
+    #[test]
+    fn timed_analysis_keeps_to_its_time() {
+        let start = "r6b/8/8/8/8/8/8/b6r r 0";
+        let timed = analyze("alphabeta:time=200", start);
+        assert!(timed.depth >= 1);
+        assert!(timed.time < Duration::from_secs(2), "{:?}", timed.time);
+    }
+
+    #[test]
+    fn stopped_analyses_give_up() {
+        let stop = Arc::new(AtomicBool::new(true));
+        let analyzer = Analyzer::from_spec(&"alphabeta:depth=64".parse().unwrap())
+            .unwrap()
+            .stop_flag(stop);
+        let position: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+        let board = position.board();
+        let analysis = analyzer.analyze(&position.configuration(&board)).unwrap();
+        // the first iteration searches nothing and always completes
+        assert_eq!(analysis.depth, 1);
+        assert!(analysis.time < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_search_engines_analyze() {
+        assert!(Analyzer::from_spec(&"greedy".parse().unwrap()).is_err());
+        assert!(Analyzer::from_spec(&"human".parse().unwrap()).is_err());
+    }
+}
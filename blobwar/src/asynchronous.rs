@@ -0,0 +1,479 @@
+//! Asynchronous (tokio) network player and lobby server, with the `async` feature.
+//!
+//! Same protocol as `protocol`, but all clients of a server share a few threads : each
+//! connection and each game is a tokio task. Clients `pair` to be matched with the next client
+//! asking for it (like in `lobby`, so `client --lobby` works) and may ask for the leaderboard.
+//!
+//! Messages are lines of at most `MAX_MESSAGE` bytes. Writes wait for the client to read them,
+//! a slow client only slows down its own game (and its clock keeps running). Games are cancelled
+//! when the server shuts down, both players receiving an `error`.
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, Notify};
+use tokio::time::timeout;
+
+use crate::configuration::{Configuration, Movement};
+use crate::elo::Score;
+use crate::lobby::{Leaderboard, LobbySettings, Name, Names};
+use crate::notation::Fen;
+use crate::protocol::{Color, Message, PROTOCOL_VERSION};
+use crate::record::{ForfeitReason, GameRecord, RecordedMove, Termination};
+
+/// Longest message accepted, in bytes.
+pub const MAX_MESSAGE: usize = 64 * 1024;
+
+/// Messages over a TCP stream, one JSON object per line.
+pub struct AsyncConnection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    line: Vec<u8>,
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl AsyncConnection {
+    /// Speak the protocol on given stream.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        Ok(AsyncConnection {
+            reader: BufReader::new(reader),
+            writer,
+            line: Vec::new(),
+        })
+    }
+
+    /// Send given message, waiting for the client to make room for it.
+    pub async fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mut line = serde_json::to_vec(message).expect("failed serializing message");
+        line.push(b'\n');
+        self.writer.write_all(&line).await
+    }
+
+    /// Wait for next message. Fails on closed connections, invalid and too long messages.
+    pub async fn receive(&mut self) -> io::Result<Message> {
+        self.line.clear();
+        let limit = MAX_MESSAGE as u64 + 1;
+        let read = (&mut self.reader)
+            .take(limit)
+            .read_until(b'\n', &mut self.line)
+            .await?;
+        if !self.line.ends_with(b"\n") {
+            return Err(if read as u64 == limit {
+                invalid_data("message too long")
+            } else {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")
+            });
+        }
+        serde_json::from_slice(&self.line).map_err(invalid_data)
+    }
+
+    /// Send our `hello` and check the other side's one, returning its name.
+    pub async fn handshake(&mut self, name: &str) -> io::Result<String> {
+        self.send(&Message::Hello {
+            name: name.to_owned(),
+            version: PROTOCOL_VERSION,
+        })
+        .await?;
+        match self.receive().await? {
+            Message::Hello { name, version } if version == PROTOCOL_VERSION => Ok(name),
+            Message::Hello { version, .. } => {
+                let message = format!(
+                    "unsupported protocol version {} (we speak {})",
+                    version, PROTOCOL_VERSION
+                );
+                let _ = self
+                    .send(&Message::Error {
+                        message: message.clone(),
+                    })
+                    .await;
+                Err(invalid_data(message))
+            }
+            other => Err(invalid_data(format!("expected hello, got {:?}", other))),
+        }
+    }
+}
+
+/// A remote client playing games.
+///
+/// Like `NetworkPlayer`, illegal moves, timeouts and disconnections forfeit the game.
+pub struct AsyncNetworkPlayer {
+    connection: AsyncConnection,
+    name: String,
+    /// Starting position of the game, until we send it in `new_game`.
+    start: Option<Fen>,
+}
+
+impl AsyncNetworkPlayer {
+    /// Create a new network player, exchanging `hello` messages with the client.
+    pub async fn new(stream: TcpStream) -> io::Result<Self> {
+        let mut connection = AsyncConnection::new(stream)?;
+        let name = connection.handshake("blobwar server").await?;
+        Ok(AsyncNetworkPlayer::from_connection(connection, name))
+    }
+
+    /// Network player on a connection where `hello` messages were already exchanged.
+    pub fn from_connection(connection: AsyncConnection, name: String) -> Self {
+        AsyncNetworkPlayer {
+            connection,
+            name,
+            start: None,
+        }
+    }
+
+    /// Name given by the client.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the connection back, to play other games.
+    pub fn into_connection(self) -> AsyncConnection {
+        self.connection
+    }
+
+    /// Ask the client for its move, giving it at most `time` (sending included).
+    pub async fn next_move(
+        &mut self,
+        configuration: &Configuration<'_>,
+        time: Option<Duration>,
+    ) -> Result<Option<Movement>, ForfeitReason> {
+        let exchange = async {
+            if let Some(start) = self.start {
+                self.connection
+                    .send(&Message::NewGame {
+                        board: start.to_string(),
+                        color: Color::from_player(configuration.current_player),
+                    })
+                    .await?;
+                self.start = None;
+            }
+            self.connection
+                .send(&Message::Position {
+                    position: Fen::from_configuration(configuration, 0).to_string(),
+                    clock: time.map(|t| t.as_millis() as u64),
+                })
+                .await?;
+            self.connection.receive().await
+        };
+        let answer = match time {
+            Some(time) => timeout(time, exchange)
+                .await
+                .map_err(|_| ForfeitReason::Timeout)?,
+            None => exchange.await,
+        };
+        let result = match answer {
+            Ok(Message::Move { movement: Some(m) }) if !configuration.check_move(&m) => {
+                Err(ForfeitReason::IllegalMove)
+            }
+            Ok(Message::Move { movement: None }) if configuration.movements().next().is_some() => {
+                Err(ForfeitReason::IllegalMove)
+            }
+            Ok(Message::Move { movement }) => Ok(movement),
+            Ok(_) | Err(_) => Err(ForfeitReason::Disconnection),
+        };
+        if let Err(reason) = result {
+            let _ = self
+                .connection
+                .send(&Message::Error {
+                    message: format!("you forfeit the game ({})", reason),
+                })
+                .await;
+        }
+        result
+    }
+
+    /// A game starts from given position, the client hears of it before its first move.
+    pub fn game_started(&mut self, start: &Fen) {
+        self.start = Some(*start);
+    }
+
+    /// Tell the client the game is over.
+    pub async fn game_over(&mut self, end: &Configuration<'_>, termination: Option<&Termination>) {
+        self.start = None;
+        let _ = self
+            .connection
+            .send(&Message::GameOver {
+                result: end.red_value(),
+                termination: termination.map(|t| t.to_string()),
+                position: Some(Fen::from_configuration(end, 0).to_string()),
+            })
+            .await;
+    }
+}
+
+/// Play a game between two remote players from given position, `red` playing red.
+/// Each player has `clock` of thinking time for the whole game.
+pub async fn play_game(
+    start: &Fen,
+    red: &mut AsyncNetworkPlayer,
+    blue: &mut AsyncNetworkPlayer,
+    clock: Option<Duration>,
+) -> GameRecord {
+    let board = start.board();
+    let mut configuration = start.configuration(&board);
+    let mut record = GameRecord::new(&configuration, red.name(), blue.name());
+    let mut remaining = [clock.unwrap_or_default(); 2];
+    red.game_started(start);
+    blue.game_started(start);
+    while !configuration.game_over() {
+        let side = configuration.current_player;
+        let player = if side { &mut *blue } else { &mut *red };
+        let time = clock.map(|_| remaining[side as usize]);
+        let start = Instant::now();
+        let result = player.next_move(&configuration, time).await;
+        let elapsed = start.elapsed();
+        remaining[side as usize] = remaining[side as usize].saturating_sub(elapsed);
+        let movement = match result {
+            Ok(movement) => movement,
+            Err(reason) => {
+                record.termination = Some(Termination::Forfeit(side, reason));
+                break;
+            }
+        };
+        record.moves.push(RecordedMove {
+            movement,
+            time: Some(elapsed.as_millis() as u64),
+            ..Default::default()
+        });
+        configuration = match movement {
+            Some(movement) => configuration.play(&movement),
+            None => configuration.skip_play(),
+        };
+    }
+    record.result = Some(configuration.red_value());
+    red.game_over(&configuration, record.termination.as_ref())
+        .await;
+    blue.game_over(&configuration, record.termination.as_ref())
+        .await;
+    record
+}
+
+#[derive(Default)]
+struct State {
+    /// Client waiting after a `pair`.
+    pairing: Option<(Name, AsyncConnection)>,
+    /// Games played so far.
+    played: usize,
+    /// Games in progress.
+    running: usize,
+    scores: HashMap<String, Score>,
+}
+
+/// Lobby server running all its clients and games as tokio tasks.
+#[derive(Clone)]
+pub struct AsyncLobby {
+    settings: Arc<LobbySettings>,
+    start: Fen,
+    state: Arc<Mutex<State>>,
+    names: Names,
+    /// Set when shutting down.
+    stop: Arc<watch::Sender<bool>>,
+    /// Notified at the end of each game.
+    finished: Arc<Notify>,
+}
+
+impl AsyncLobby {
+    /// New empty lobby.
+    pub fn new(settings: LobbySettings) -> Result<Self, String> {
+        let start = Fen::load(&settings.board)?;
+        Ok(AsyncLobby {
+            settings: Arc::new(settings),
+            start,
+            state: Arc::new(Mutex::new(State::default())),
+            names: Names::default(),
+            stop: Arc::new(watch::channel(false).0),
+            finished: Arc::new(Notify::new()),
+        })
+    }
+
+    /// Current leaderboard.
+    pub fn leaderboard(&self) -> Leaderboard {
+        Leaderboard::new(&self.state.lock().unwrap().scores)
+    }
+
+    /// Accept clients on given listener until `shutdown` completes, then cancel all games and
+    /// wait for them to stop.
+    pub async fn serve<F: Future<Output = ()>>(
+        self,
+        listener: TcpListener,
+        shutdown: F,
+    ) -> io::Result<()> {
+        if let Some(directory) = &self.settings.records {
+            std::fs::create_dir_all(directory)?;
+        }
+        tokio::pin!(shutdown);
+        loop {
+            let (stream, origin) = tokio::select! {
+                _ = &mut shutdown => break,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        eprintln!("lobby: accept failed: {}", e);
+                        continue;
+                    }
+                },
+            };
+            let lobby = self.clone();
+            tokio::spawn(async move {
+                let connection = AsyncConnection::new(stream);
+                let mut connection = match connection {
+                    Ok(connection) => connection,
+                    Err(e) => return eprintln!("lobby: client {} failed: {}", origin, e),
+                };
+                match connection.handshake("blobwar lobby").await {
+                    Ok(name) => match lobby.names.take(name.clone()) {
+                        Some(name) => {
+                            println!("{} joined from {}", name, origin);
+                            lobby.welcome(name, connection).await
+                        }
+                        None => {
+                            let _ = connection
+                                .send(&Message::Error {
+                                    message: format!("{} is already playing here", name),
+                                })
+                                .await;
+                        }
+                    },
+                    Err(e) => eprintln!("lobby: client {} failed: {}", origin, e),
+                }
+            });
+        }
+        self.stop.send_replace(true);
+        // games start under the state lock after checking `stop` : all of them are counted now
+        loop {
+            let finished = self.finished.notified();
+            if self.state.lock().unwrap().running == 0 {
+                return Ok(());
+            }
+            finished.await;
+        }
+    }
+
+    /// Answer lobby requests until the client is waiting for or playing a game.
+    // boxed since games welcome their players back
+    fn welcome(
+        self,
+        name: Name,
+        mut connection: AsyncConnection,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async move {
+            loop {
+                let answer = match connection.receive().await {
+                    Ok(Message::Pair) => {
+                        let mut state = self.state.lock().unwrap();
+                        // under the lock : shutdowns either see the game running or stop it here
+                        if *self.stop.borrow() {
+                            Message::Error {
+                                message: "server shutting down".to_owned(),
+                            }
+                        } else {
+                            match state.pairing.take() {
+                                Some(host) => {
+                                    state.running += 1;
+                                    drop(state);
+                                    tokio::spawn(self.clone().run_game(host, (name, connection)));
+                                }
+                                None => state.pairing = Some((name, connection)),
+                            }
+                            return;
+                        }
+                    }
+                    Ok(Message::GetLeaderboard) => Message::Leaderboard {
+                        entries: self.leaderboard().0,
+                    },
+                    Ok(other) => Message::Error {
+                        message: format!("unexpected message {:?}", other),
+                    },
+                    Err(e) => return println!("{} left ({})", name, e),
+                };
+                if connection.send(&answer).await.is_err() {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Play a game (already counted as running), the host being red, then send both clients
+    /// back to the lobby.
+    async fn run_game(self, host: (Name, AsyncConnection), guest: (Name, AsyncConnection)) {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            state.played += 1;
+            state.played
+        };
+        let mut red = AsyncNetworkPlayer::from_connection(host.1, host.0.to_string());
+        let mut blue = AsyncNetworkPlayer::from_connection(guest.1, guest.0.to_string());
+        let mut stop = self.stop.subscribe();
+        let record = tokio::select! {
+            record = play_game(&self.start, &mut red, &mut blue, self.settings.clock) => Some(record),
+            _ = async { stop.wait_for(|&stop| stop).await.is_ok() } => None,
+        };
+        let record = match record {
+            Some(record) => record,
+            None => {
+                for player in [&mut red, &mut blue] {
+                    let _ = player
+                        .connection
+                        .send(&Message::Error {
+                            message: "server shutting down".to_owned(),
+                        })
+                        .await;
+                }
+                self.state.lock().unwrap().running -= 1;
+                self.finished.notify_waiters();
+                return;
+            }
+        };
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let value = match record.winner() {
+                None => 0,
+                Some(false) => 1,
+                Some(true) => -1,
+            };
+            state
+                .scores
+                .entry(red.name().to_owned())
+                .or_default()
+                .add(value);
+            state
+                .scores
+                .entry(blue.name().to_owned())
+                .or_default()
+                .add(-value);
+        }
+        if let Some(directory) = &self.settings.records {
+            save(record, directory.join(format!("game_{:05}.txt", id))).await;
+        }
+        for (name, player) in [(host.0, red), (guest.0, blue)] {
+            tokio::spawn(self.clone().welcome(name, player.into_connection()));
+        }
+        self.state.lock().unwrap().running -= 1;
+        self.finished.notify_waiters();
+    }
+}
+
+/// Save given record without blocking other tasks.
+async fn save<P: AsRef<Path> + Send + 'static>(record: GameRecord, path: P) {
+    let saved = tokio::task::spawn_blocking(move || {
+        record
+            .save(&path)
+            .map_err(|e| format!("failed saving {}: {}", path.as_ref().display(), e))
+    })
+    .await;
+    if let Ok(Err(e)) = saved {
+        eprintln!("lobby: {}", e);
+    }
+}
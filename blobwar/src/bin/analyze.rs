@@ -0,0 +1,65 @@
+use blobwar::analysis::Analyzer;
+use blobwar::notation::Fen;
+use blobwar::strategy::EngineSpec;
+
+use std::env::args;
+use std::process::exit;
+
+const USAGE: &str = "usage: analyze [--engine ENGINE] [--multipv N] POSITION
+rank all legal moves of a position (or board file) with their principal variation;
+engine specs look like alphabeta:depth=6 or minmax:time=2000";
+
+fn main() {
+    let mut spec: EngineSpec = "alphabeta:time=1000".parse().unwrap();
+    let mut multi_pv = None;
+    let mut position = None;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--engine" => spec = value().parse().unwrap_or_else(|e: String| usage(&e)),
+            "--multipv" => {
+                multi_pv = Some(value().parse().unwrap_or_else(|_| usage("invalid multipv")))
+            }
+            "--help" | "-h" => usage(""),
+            other => position = Some(other.to_owned()),
+        }
+    }
+    let position = Fen::load(&position.unwrap_or_else(|| usage("missing position")))
+        .unwrap_or_else(|e| usage(&e));
+    let analyzer = Analyzer::from_spec(&spec).unwrap_or_else(|e| usage(&e));
+
+    let board = position.board();
+    let configuration = position.configuration(&board);
+    println!("position: {}", position);
+    println!("{}", configuration);
+    let mut analysis = analyzer
+        .analyze_with(&configuration, |analysis| {
+            println!(
+                "info depth {} nodes {} time {}ms best {}",
+                analysis.depth,
+                analysis.nodes,
+                analysis.time.as_millis(),
+                analysis.best().map_or(0, |best| best.score)
+            )
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("analysis failed: {}", e);
+            exit(1)
+        });
+    if analysis.moves.is_empty() {
+        println!("game over");
+        return;
+    }
+    analysis.multi_pv = multi_pv;
+    print!("{}", analysis);
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -0,0 +1,65 @@
+use blobwar::asynchronous::AsyncLobby;
+use blobwar::lobby::LobbySettings;
+
+use std::env::args;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+use tokio::net::TcpListener;
+
+const USAGE: &str = "usage: async_lobby [--bind ADDRESS] [--port PORT] [--board BOARD] \
+[--clock MS] [--records DIR]
+all clients and games share a few threads ; clients connect with: client ADDRESS ENGINE --lobby
+ctrl-c cancels the games in progress and stops the server";
+
+#[tokio::main]
+async fn main() {
+    let mut settings = LobbySettings::default();
+    let mut bind = "0.0.0.0".to_owned();
+    let mut port = blobwar::strategy::registry::DEFAULT_PORT;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--bind" => bind = value(),
+            "--port" => port = value().parse().unwrap_or_else(|_| usage("invalid port")),
+            "--board" => settings.board = value(),
+            "--clock" => {
+                settings.clock = Some(Duration::from_millis(
+                    value().parse().unwrap_or_else(|_| usage("invalid clock")),
+                ))
+            }
+            "--records" => settings.records = Some(PathBuf::from(value())),
+            "--help" | "-h" => usage(""),
+            other => usage(&format!("unexpected argument {}", other)),
+        }
+    }
+
+    let lobby = AsyncLobby::new(settings).unwrap_or_else(|e| usage(&e));
+    let listener = TcpListener::bind((bind.as_str(), port))
+        .await
+        .unwrap_or_else(|e| usage(&e.to_string()));
+    println!("lobby listening on {}:{}", bind, port);
+    let shutdown = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("failed waiting for ctrl-c: {}", e);
+            std::future::pending::<()>().await
+        }
+    };
+    lobby
+        .clone()
+        .serve(listener, shutdown)
+        .await
+        .unwrap_or_else(|e| usage(&e.to_string()));
+    print!("{}", lobby.leaderboard());
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -1,26 +1,23 @@
-use blobwar::board::Board;
-use blobwar::configuration::Configuration;
-use blobwar::strategy::{alpha_beta_anytime, alpha_beta_pass_anytime, min_max_anytime, alpha_beta_table_anytime, min_max_par_anytime};
+use blobwar::notation::Fen;
+use blobwar::strategy::Registry;
 use std::env;
 
 fn main() {
-    let config_string = env::args()
+    let position: Fen = env::args()
         .nth(1)
-        .expect("missing argument to iterative deepening");
-    let strategy_index = env::args()
+        .expect("missing argument to iterative deepening")
+        .parse()
+        .expect("invalid position");
+    let strategy_name = env::args()
         .nth(2)
         .expect("missing argument to iterative deepening");
-    let board = Board::deserialize(&config_string);
-    let configuration = Configuration::deserialize(&config_string, &board);
-    match strategy_index
-        .parse::<usize>()
-        .expect("error parsing strategy integer")
-    {
-        0 => min_max_anytime(&configuration),
-        1 => alpha_beta_anytime(&configuration),
-        2 => alpha_beta_pass_anytime(&configuration),
-        3 => alpha_beta_table_anytime(&configuration),
-        4 => min_max_par_anytime(&configuration),
-        _ => panic!("invalid strategy number"),
-    }
+    let segment = env::args()
+        .nth(3)
+        .expect("missing argument to iterative deepening");
+    let board = position.board();
+    let configuration = position.configuration(&board);
+    let anytime = Registry::default()
+        .anytime(&strategy_name)
+        .expect("invalid strategy name");
+    anytime(&configuration, &segment);
 }
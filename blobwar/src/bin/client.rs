@@ -1,17 +1,342 @@
 use blobwar::board::Board;
-use blobwar::configuration::Configuration;
-use blobwar::strategy::{IterativeDeepening, IterativeStrategy, Strategy};
+use blobwar::configuration::{Configuration, Movement};
+use blobwar::notation::Fen;
+use blobwar::protocol::{Color, Connection, Message};
+use blobwar::record::{GameRecord, RecordedMove};
+use blobwar::strategy::registry::DEFAULT_PORT;
+use blobwar::strategy::{EngineSpec, Registry, Strategy};
 
 use std::env::args;
-use std::io::Write;
+use std::io::{self, Write};
 use std::io::{BufRead, BufReader};
 use std::net::TcpStream;
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: client [--host HOST] [--port PORT] [--engine SPEC] [--time MS] \
+[--name NAME] [--games N] [--record FILE] [--lobby] [--legacy] [HOST [SPEC]]
+default is alphabeta:time=1000 on port 12345, named after its engine spec
+with --record, games are saved into FILE.1, FILE.2, ... (FILE itself with --games 1)";
+
+/// Time between two reconnection attempts.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Client settings.
+struct Settings {
+    host: String,
+    port: u16,
+    name: String,
+    /// Stop after this many games.
+    games: Option<usize>,
+    record: Option<String>,
+    /// Ask a lobby to pair us after each game.
+    lobby: bool,
+}
 
 fn main() {
-    let address = args().nth(1).expect("missing machine name or IP address");
-    let mut strategy = IterativeDeepening::new(IterativeStrategy::AlphaBeta);
-    let mut sending =
-        TcpStream::connect((address.as_str(), 12_345)).expect("failed connecting to server");
+    let mut host = None;
+    let mut port = DEFAULT_PORT;
+    let mut spec: Option<EngineSpec> = None;
+    let mut time = None;
+    let mut name = None;
+    let mut games = None;
+    let mut record = None;
+    let mut lobby = false;
+    let mut legacy = false;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--host" => host = Some(value()),
+            "--port" => port = value().parse().unwrap_or_else(|_| usage("invalid port")),
+            "--engine" => spec = Some(value().parse().unwrap_or_else(|e: String| usage(&e))),
+            "--time" => time = Some(value().parse().unwrap_or_else(|_| usage("invalid time"))),
+            "--name" => name = Some(value()),
+            "--games" => games = Some(value().parse().unwrap_or_else(|_| usage("invalid games"))),
+            "--record" => record = Some(value()),
+            "--lobby" => lobby = true,
+            "--legacy" => legacy = true,
+            "--help" | "-h" => usage(""),
+            other if host.is_none() => host = Some(other.to_owned()),
+            other if spec.is_none() => {
+                spec = Some(other.parse().unwrap_or_else(|e: String| usage(&e)))
+            }
+            other => usage(&format!("unexpected argument {}", other)),
+        }
+    }
+    let mut spec = spec.unwrap_or_else(|| "alphabeta:time=1000".parse().unwrap());
+    if time.is_some() {
+        spec.time = time;
+        spec.depth = None;
+    }
+    let strategy = Registry::default()
+        .build(&spec)
+        .unwrap_or_else(|e| usage(&e));
+    let settings = Settings {
+        host: host.unwrap_or_else(|| usage("missing machine name or IP address")),
+        port,
+        name: name.unwrap_or_else(|| spec.label()),
+        games,
+        record,
+        lobby,
+    };
+
+    if legacy {
+        let stream = TcpStream::connect((settings.host.as_str(), settings.port))
+            .expect("failed connecting to server");
+        play_legacy(stream, strategy);
+    } else {
+        play(&settings, strategy);
+    }
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
+
+/// Game being played, rebuilt from the positions the server sends.
+struct Game {
+    start: Fen,
+    color: Color,
+    /// Last position we know of.
+    last: Fen,
+    moves: Vec<RecordedMove>,
+    /// Did we fail following the game ?
+    lost_track: bool,
+}
+
+/// Move leading from `before` to `after` (`None` for a pass), if any.
+fn find_move(before: &Fen, after: &Fen) -> Option<Option<Movement>> {
+    let board = before.board();
+    let configuration = before.configuration(&board);
+    let reaches = |c: &Configuration| Fen::from_configuration(c, after.ply) == *after;
+    if reaches(&configuration.skip_play()) {
+        return Some(None);
+    }
+    let movement = configuration
+        .movements()
+        .find(|m| reaches(&configuration.play(m)));
+    movement.map(Some)
+}
+
+impl Game {
+    fn new(start: Fen, color: Color) -> Self {
+        Game {
+            start,
+            color,
+            last: start,
+            moves: Vec::new(),
+            lost_track: false,
+        }
+    }
+
+    /// Record the adversary's move leading to given position.
+    fn reach(&mut self, position: Fen) {
+        if self.last == position {
+            return;
+        }
+        match find_move(&self.last, &position) {
+            Some(movement) => self.moves.push(RecordedMove {
+                movement,
+                ..Default::default()
+            }),
+            None => self.lost_track = true,
+        }
+        self.last = position;
+    }
+
+    /// Record our move, played from the last position.
+    fn play(&mut self, movement: Option<Movement>, time: Duration) {
+        let board = self.last.board();
+        let configuration = self.last.configuration(&board);
+        let after = match &movement {
+            Some(m) => configuration.play(m),
+            None => configuration.skip_play(),
+        };
+        self.last = Fen::from_configuration(&after, self.last.ply);
+        self.moves.push(RecordedMove {
+            movement,
+            time: Some(time.as_millis() as u64),
+            ..Default::default()
+        });
+    }
+
+    /// Final record of the game.
+    fn record(self, name: &str, result: i8, termination: Option<String>) -> GameRecord {
+        let board = self.start.board();
+        let (red, blue) = match self.color {
+            Color::Red => (name, "adversary"),
+            Color::Blue => ("adversary", name),
+        };
+        let mut record = GameRecord::new(&self.start.configuration(&board), red, blue);
+        record.moves = self.moves;
+        record.result = Some(result);
+        record.termination = termination.and_then(|t| t.parse().ok());
+        record
+    }
+}
+
+/// Connect and exchange `hello` messages.
+fn connect(settings: &Settings) -> io::Result<Connection> {
+    let stream = TcpStream::connect((settings.host.as_str(), settings.port))?;
+    let mut connection = Connection::new(stream)?;
+    let server = connection.handshake(&settings.name)?;
+    println!("connected to {}", server);
+    Ok(connection)
+}
+
+/// Connect again within the grace period and take our session over.
+fn resume(settings: &Settings, token: &str, grace: Duration) -> io::Result<Connection> {
+    let deadline = Instant::now() + grace;
+    loop {
+        match connect(settings) {
+            Ok(mut connection) => {
+                connection.send(&Message::Resume {
+                    token: token.to_owned(),
+                })?;
+                return match connection.receive()? {
+                    Message::Session { .. } => Ok(connection),
+                    Message::Error { message } => Err(io::Error::other(message)),
+                    other => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("expected session, got {:?}", other),
+                    )),
+                };
+            }
+            Err(e) if Instant::now() >= deadline => return Err(e),
+            Err(_) => thread::sleep(RETRY_DELAY),
+        }
+    }
+}
+
+/// Save the record of our `index`th game (counting from 1).
+fn save(settings: &Settings, index: usize, record: &GameRecord) {
+    if let Some(path) = &settings.record {
+        let path = if settings.games == Some(1) {
+            path.clone()
+        } else {
+            format!("{}.{}", path, index)
+        };
+        match record.save(&path) {
+            Ok(()) => println!("game saved into {}", path),
+            Err(e) => println!("failed saving game into {}: {}", path, e),
+        }
+    }
+}
+
+/// Speak the versioned protocol until the server closes the connection or we played enough
+/// games. In a lobby, ask to be paired again after each game. If the server gave us a session,
+/// reconnect when losing the connection during a game.
+fn play(settings: &Settings, mut strategy: Box<dyn Strategy>) {
+    let mut connection = connect(settings).expect("failed connecting to server");
+    if settings.lobby {
+        connection.send(&Message::Pair).expect("pairing failed");
+    }
+    let mut session: Option<(String, Duration)> = None;
+    let mut game: Option<Game> = None;
+    let mut played = 0;
+    loop {
+        let message = match (connection.receive(), &session) {
+            (Ok(message), _) => message,
+            (Err(e), Some((token, grace))) if game.is_some() => {
+                println!("connection lost: {}", e);
+                match resume(settings, token, *grace) {
+                    Ok(resumed) => {
+                        connection = resumed;
+                        continue;
+                    }
+                    Err(e) => {
+                        println!("failed resuming session: {}", e);
+                        return;
+                    }
+                }
+            }
+            (Err(e), _) => {
+                println!("connection ended: {}", e);
+                return;
+            }
+        };
+        match message {
+            Message::Session { token, grace } => {
+                session = Some((token, Duration::from_millis(grace)))
+            }
+            Message::NewGame { board, color } => {
+                println!("new game as {:?} on {}", color, board);
+                let start = board.parse().expect("invalid board from server");
+                game = Some(Game::new(start, color));
+            }
+            Message::Position { position, .. } => {
+                let position: Fen = position.parse().expect("invalid position from server");
+                let board = position.board();
+                let start = Instant::now();
+                let movement = strategy.compute_next_move(&position.configuration(&board));
+                if let Some(game) = &mut game {
+                    game.reach(position);
+                    game.play(movement, start.elapsed());
+                }
+                // a lost connection shows up when receiving next
+                if let Err(e) = connection.send(&Message::Move { movement }) {
+                    println!("failed sending move: {}", e);
+                }
+            }
+            Message::GameOver {
+                result,
+                termination,
+                position,
+            } => {
+                let end = position.map(|p| p.parse::<Fen>().expect("invalid position from server"));
+                if let Some(end) = end {
+                    let board = end.board();
+                    strategy.game_over(&end.configuration(&board), None);
+                }
+                match &termination {
+                    Some(termination) => println!("game over: {}", termination),
+                    None => println!("game over: red value of {}", result),
+                }
+                played += 1;
+                if let Some(mut game) = game.take() {
+                    if let Some(end) = end {
+                        game.reach(end);
+                    }
+                    if game.lost_track {
+                        println!("lost track of the game, not saving it");
+                    } else {
+                        save(
+                            settings,
+                            played,
+                            &game.record(&settings.name, result, termination),
+                        );
+                    }
+                }
+                if settings.games == Some(played) {
+                    return;
+                }
+                if settings.lobby {
+                    connection.send(&Message::Pair).expect("pairing failed");
+                }
+            }
+            Message::Error { message } => println!("server error: {}", message),
+            Message::Leaderboard { entries } => {
+                for entry in entries {
+                    println!(
+                        "{}: +{} ={} -{}",
+                        entry.name, entry.wins, entry.draws, entry.losses
+                    );
+                }
+            }
+            other => println!("unexpected message {:?}", other),
+        }
+    }
+}
+
+/// Answer serialized configurations with JSON moves.
+fn play_legacy(mut sending: TcpStream, mut strategy: Box<dyn Strategy>) {
     sending.set_nodelay(true).expect("failed setting no delay");
     let receiving = BufReader::new(sending.try_clone().expect("failed cloning socket"));
 
@@ -23,6 +348,6 @@ fn main() {
         let game = Configuration::deserialize(&line, &board);
         let next_move = strategy.compute_next_move(&game);
         serde_json::to_writer(&mut sending, &next_move).expect("sending back movement failed");
-        sending.write(b"\n").expect("newline failed");
+        sending.write_all(b"\n").expect("newline failed");
     }
 }
@@ -0,0 +1,76 @@
+// external engines need processes and unix sockets
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use blobwar::engine::serve;
+    use blobwar::strategy::registry::SEARCH_ENGINES;
+    use blobwar::strategy::EngineSpec;
+
+    use std::env::args;
+    use std::io::{self, BufReader};
+    use std::os::unix::net::UnixListener;
+    use std::process::exit;
+
+    const USAGE: &str = "usage: engine [--socket PATH] [SPEC]
+speak the text engine protocol on stdin/stdout (on a unix socket with --socket) with the
+strategy of SPEC (default alphabeta:depth=4), changed with setoption; available strategies:";
+
+    pub fn main() {
+        let mut socket = None;
+        let mut spec: Option<EngineSpec> = None;
+
+        let mut arguments = args().skip(1);
+        while let Some(argument) = arguments.next() {
+            match argument.as_str() {
+                "--socket" => socket = Some(arguments.next().unwrap_or_else(|| usage(&argument))),
+                "--help" | "-h" => usage(""),
+                other if spec.is_none() => {
+                    spec = Some(other.parse().unwrap_or_else(|e: String| usage(&e)))
+                }
+                other => usage(&format!("unexpected argument {}", other)),
+            }
+        }
+        let spec = spec.unwrap_or_else(|| "alphabeta:depth=4".parse().unwrap());
+        if !SEARCH_ENGINES.contains(&spec.strategy.as_str()) {
+            usage(&format!("{} is not a search engine", spec.strategy));
+        }
+
+        let result = match socket {
+            None => serve(spec, BufReader::new(io::stdin()), io::stdout()),
+            Some(path) => {
+                let listener = UnixListener::bind(&path).unwrap_or_else(|e| usage(&e.to_string()));
+                eprintln!("engine listening on {}", path);
+                // one controller at a time
+                for stream in listener.incoming() {
+                    let served = stream.and_then(|stream| {
+                        serve(spec.clone(), BufReader::new(stream.try_clone()?), stream)
+                    });
+                    if let Err(e) = served {
+                        eprintln!("controller lost: {}", e);
+                    }
+                }
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("engine failed: {}", e);
+            exit(1);
+        }
+    }
+
+    fn usage(error: &str) -> ! {
+        if !error.is_empty() {
+            eprintln!("{}", error);
+        }
+        eprintln!("{}", USAGE);
+        eprintln!("{}", SEARCH_ENGINES.join(", "));
+        exit(1)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    native::main()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
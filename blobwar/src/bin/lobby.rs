@@ -0,0 +1,47 @@
+use blobwar::lobby::{Lobby, LobbySettings};
+
+use std::env::args;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+const USAGE: &str = "usage: lobby [--bind ADDRESS] [--port PORT] [--board BOARD] [--clock MS] \
+[--records DIR]
+clients connect with: client ADDRESS ENGINE --lobby";
+
+fn main() {
+    let mut settings = LobbySettings::default();
+    let mut bind = "0.0.0.0".to_owned();
+    let mut port = blobwar::strategy::registry::DEFAULT_PORT;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--bind" => bind = value(),
+            "--port" => port = value().parse().unwrap_or_else(|_| usage("invalid port")),
+            "--board" => settings.board = value(),
+            "--clock" => {
+                settings.clock = Some(Duration::from_millis(
+                    value().parse().unwrap_or_else(|_| usage("invalid clock")),
+                ))
+            }
+            "--records" => settings.records = Some(PathBuf::from(value())),
+            "--help" | "-h" => usage(""),
+            other => usage(&format!("unexpected argument {}", other)),
+        }
+    }
+
+    println!("lobby listening on {}:{}", bind, port);
+    Lobby::new(settings)
+        .serve((bind.as_str(), port))
+        .unwrap_or_else(|e| usage(&e.to_string()));
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -0,0 +1,84 @@
+use blobwar::png;
+use blobwar::record::GameRecord;
+use blobwar::render::Markers;
+use blobwar::svg;
+
+use std::env::args;
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+
+const USAGE: &str = "usage: render [--output DIR] [--png] [--animate] [--delay MS] RECORD_FILE
+writes DIR/ply_000.svg, DIR/ply_001.svg... or with --animate a single DIR/game.svg
+(PNG pictures with --png)";
+
+fn main() {
+    let mut output = PathBuf::from("render");
+    let mut animate = false;
+    let mut as_png = false;
+    let mut delay = 1000;
+    let mut path = None;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--output" => output = PathBuf::from(value()),
+            "--animate" => animate = true,
+            "--png" => as_png = true,
+            "--delay" => delay = value().parse().unwrap_or_else(|_| usage("invalid delay")),
+            "--help" | "-h" => usage(""),
+            file if path.is_none() => path = Some(file.to_owned()),
+            other => usage(&format!("unexpected argument {}", other)),
+        }
+    }
+    let path = path.unwrap_or_else(|| usage("missing record file"));
+
+    let record = GameRecord::load(&path).unwrap_or_else(|e| usage(&e.to_string()));
+    let board = record.board();
+    let configurations = record.replay(&board).unwrap_or_else(|e| usage(&e));
+    let markers: Vec<Markers> = std::iter::once(Markers::default())
+        .chain(
+            configurations
+                .iter()
+                .zip(&record.moves)
+                .map(|(before, recorded)| Markers::new(before, &recorded.movement)),
+        )
+        .collect();
+
+    fs::create_dir_all(&output).expect("failed creating output directory");
+    let extension = if as_png { "png" } else { "svg" };
+    if animate {
+        let file = output.join(format!("game.{}", extension));
+        let animation = if as_png {
+            png::animation(&configurations, &markers, delay)
+        } else {
+            svg::animation(&configurations, &markers, delay).into_bytes()
+        };
+        fs::write(&file, animation).expect("failed writing animation");
+        println!("wrote {}", file.display());
+    } else {
+        for (ply, (configuration, markers)) in configurations.iter().zip(&markers).enumerate() {
+            let file = output.join(format!("ply_{:03}.{}", ply, extension));
+            let diagram = if as_png {
+                png::diagram(configuration, markers)
+            } else {
+                svg::diagram(configuration, markers).into_bytes()
+            };
+            fs::write(&file, diagram).expect("failed writing diagram");
+        }
+        println!(
+            "wrote {} diagrams into {}",
+            configurations.len(),
+            output.display()
+        );
+    }
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -0,0 +1,37 @@
+use blobwar::record::GameRecord;
+use blobwar::render::{render_io, Markers, Style};
+
+use std::env::args;
+use std::io::{self, BufRead};
+
+fn main() {
+    let path = args().nth(1).expect("usage: replay RECORD_FILE [--step]");
+    let step = args().nth(2).is_some_and(|a| a == "--step");
+    let record = GameRecord::load(&path).expect("failed loading game record");
+    let board = record.board();
+    let configurations = record.replay(&board).expect("invalid game record");
+
+    println!(
+        "red: {}\nblue: {}\ndate: {}",
+        record.red, record.blue, record.date
+    );
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let style = Style::detect();
+    for (ply, configuration) in configurations.iter().enumerate() {
+        let markers = if ply > 0 {
+            println!("{}. {}", ply, record.moves[ply - 1]);
+            Markers::new(&configurations[ply - 1], &record.moves[ply - 1].movement)
+        } else {
+            Markers::default()
+        };
+        render_io(&mut io::stdout(), configuration, style, &markers).expect("failed writing board");
+        if step {
+            lines.next();
+        }
+    }
+    match record.result {
+        Some(result) => println!("result: red value of {}", result),
+        None => println!("unfinished game"),
+    }
+}
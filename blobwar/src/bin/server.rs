@@ -1,23 +1,66 @@
-use blobwar::board::Board;
-use blobwar::configuration::Configuration;
-use blobwar::strategy::{IterativeDeepening, IterativeStrategy, NetworkPlayer};
+use blobwar::server::{play_match, MatchSettings};
 
 use std::env::args;
-use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+const USAGE: &str = "usage: server [--bind ADDRESS] [--port PORT] [--games N] \
+[--boards board1,board2,...] [--clock MS] [--legacy] [--records DIR] \
+[--broadcast PORT] [--grace MS] [PLAYER PLAYER]
+players are engine specs (like alphabeta:depth=4) or remote for network clients
+default is alphabeta:time=1000 against one remote client on the standard board";
 
 fn main() {
-    let map_name = args().nth(1).unwrap_or("standard".to_owned());
-    // we need two players
-    let listener = TcpListener::bind("0.0.0.0:12345").expect("bind failed");
-    let mut players = listener
-        .incoming()
-        .map(|c| NetworkPlayer::new(c.expect("accept failed")));
+    let mut settings = MatchSettings::default();
+    let mut players = Vec::new();
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--bind" => settings.bind = value(),
+            "--port" => settings.port = value().parse().unwrap_or_else(|_| usage("invalid port")),
+            "--games" => {
+                settings.games = value().parse().unwrap_or_else(|_| usage("invalid games"))
+            }
+            "--boards" => settings.boards = value().split(',').map(|b| b.to_owned()).collect(),
+            "--clock" => {
+                settings.clock = Some(Duration::from_millis(
+                    value().parse().unwrap_or_else(|_| usage("invalid clock")),
+                ))
+            }
+            "--legacy" => settings.legacy = true,
+            "--records" => settings.records = Some(PathBuf::from(value())),
+            "--grace" => {
+                settings.grace = Some(Duration::from_millis(
+                    value().parse().unwrap_or_else(|_| usage("invalid grace")),
+                ))
+            }
+            "--broadcast" => {
+                settings.broadcast = Some(value().parse().unwrap_or_else(|_| usage("invalid port")))
+            }
+            "--help" | "-h" => usage(""),
+            player => players.push(player.parse().unwrap_or_else(|e: String| usage(&e))),
+        }
+    }
+    match players.len() {
+        0 => {}
+        2 => settings.players = [players.remove(0), players.remove(0)],
+        _ => usage("we need two players"),
+    }
 
-    // create board and start game
-    let board = Board::load(map_name).expect("failed loading map");
-    let mut game = Configuration::new(&board);
-    game.battle(
-        IterativeDeepening::new(IterativeStrategy::AlphaBeta),
-        players.next().unwrap(),
+    let result = play_match(&settings).unwrap_or_else(|e| usage(&e));
+    println!(
+        "{} against {} : {}",
+        settings.players[0], settings.players[1], result.score
     );
 }
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -0,0 +1,151 @@
+use blobwar::broadcast::DEFAULT_BROADCAST_PORT;
+use blobwar::configuration::{format_move, Movement};
+use blobwar::notation::Fen;
+use blobwar::protocol::{Connection, Message};
+use blobwar::render::{render_io, Markers, Style};
+
+use std::env::args;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::process::exit;
+
+const USAGE: &str = "usage: spectate [--port PORT] [--positions] ADDRESS
+follow the games broadcast by blobwar or server (--broadcast PORT)
+--positions prints one position per line instead of boards, for piping into other tools";
+
+fn main() {
+    let mut address = None;
+    let mut port = DEFAULT_BROADCAST_PORT;
+    let mut positions = false;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--port" => {
+                port = arguments
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or_else(|| usage("invalid port"))
+            }
+            "--positions" => positions = true,
+            "--help" | "-h" => usage(""),
+            other => address = Some(other.to_owned()),
+        }
+    }
+    let address = address.unwrap_or_else(|| usage("missing address"));
+
+    let stream = TcpStream::connect((address.as_str(), port)).unwrap_or_else(|e| {
+        eprintln!("failed connecting to {}:{}: {}", address, port, e);
+        exit(1)
+    });
+    let mut connection = Connection::new(stream).expect("failed setting up connection");
+    connection.handshake("spectator").expect("handshake failed");
+
+    let mut start: Option<Fen> = None;
+    let mut moves: Vec<Option<Movement>> = Vec::new();
+    loop {
+        let message = match connection.receive() {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("broadcast over: {}", e);
+                return;
+            }
+        };
+        match message {
+            Message::Header {
+                red,
+                blue,
+                board,
+                date,
+            } => {
+                if !positions {
+                    println!("{} (red) against {} (blue), {}", red, blue, date);
+                }
+                start = Some(board.parse().unwrap_or_else(|e: String| {
+                    eprintln!("invalid starting position: {}", e);
+                    exit(1)
+                }));
+                moves.clear();
+            }
+            Message::Played {
+                ply,
+                movement,
+                time,
+                ..
+            } => {
+                moves.truncate(ply);
+                moves.push(movement);
+                if let (false, Some(start)) = (positions, &start) {
+                    // every move, passes included, changes side
+                    let side = ["red", "blue"][(start.current_player as usize + ply) % 2];
+                    print!("{}. {} plays {}", ply + 1, side, format_move(&movement));
+                    match time {
+                        Some(time) => println!(" ({} ms)", time),
+                        None => println!(),
+                    }
+                }
+            }
+            Message::TakeBack { ply } => {
+                moves.truncate(ply);
+                if !positions {
+                    println!("moves taken back, {} left", ply);
+                }
+            }
+            Message::GameOver {
+                result,
+                termination,
+                ..
+            } => {
+                if !positions {
+                    match termination {
+                        Some(termination) => println!("game over: {}", termination),
+                        None => println!("game over: red value of {}", result),
+                    }
+                }
+                continue;
+            }
+            Message::Error { message } => {
+                eprintln!("server error: {}", message);
+                continue;
+            }
+            _ => continue,
+        }
+        if let Some(start) = &start {
+            show(start, &moves, positions).expect("failed writing board");
+        }
+    }
+}
+
+/// Display the position reached after given moves.
+fn show(start: &Fen, moves: &[Option<Movement>], positions: bool) -> io::Result<()> {
+    let board = start.board();
+    let mut configuration = start.configuration(&board);
+    let mut markers = Markers::default();
+    for movement in moves {
+        markers = Markers::new(&configuration, movement);
+        configuration = match movement {
+            Some(movement) if configuration.check_move(movement) => configuration.play(movement),
+            Some(movement) => {
+                eprintln!("illegal move {} broadcast", movement);
+                break;
+            }
+            None => configuration.skip_play(),
+        };
+    }
+    let mut stdout = io::stdout();
+    if positions {
+        let ply = start.ply + moves.len() as u32;
+        writeln!(stdout, "{}", Fen::from_configuration(&configuration, ply))?;
+    } else {
+        render_io(&mut stdout, &configuration, Style::detect(), &markers)?;
+    }
+    stdout.flush()
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
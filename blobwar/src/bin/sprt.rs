@@ -0,0 +1,89 @@
+use blobwar::elo::{match_test, Sprt, SprtStatus};
+use blobwar::notation::Fen;
+use blobwar::strategy::EngineSpec;
+
+use std::env::args;
+use std::process::exit;
+
+const USAGE: &str = "usage: sprt [--elo0 E] [--elo1 E] [--alpha A] [--beta B] [--games N] \
+[--boards board1,board2,...] [--fixed] ENGINE ENGINE
+test whether the first engine is stronger than the second one;
+with --fixed no sequential test is done and exactly N games are played
+boards are board files or positions like r6b/8/2x2x2/3xx3/3xx3/2x2x2/8/b6r";
+
+fn main() {
+    let mut sprt = Sprt::default();
+    let mut sequential = true;
+    let mut max_games = 1000;
+    let mut board_names = vec!["standard".to_owned()];
+    let mut engines = Vec::new();
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        let mut number = || -> f64 { value().parse().unwrap_or_else(|_| usage("invalid number")) };
+        match argument.as_str() {
+            "--elo0" => sprt.elo0 = number(),
+            "--elo1" => sprt.elo1 = number(),
+            "--alpha" => sprt.alpha = number(),
+            "--beta" => sprt.beta = number(),
+            "--games" => max_games = number() as usize,
+            "--boards" => board_names = value().split(',').map(|b| b.to_owned()).collect(),
+            "--fixed" => sequential = false,
+            "--help" | "-h" => usage(""),
+            spec => engines.push(spec.parse::<EngineSpec>().unwrap_or_else(|e| usage(&e))),
+        }
+    }
+    if engines.len() != 2 {
+        usage("we need exactly two engines");
+    }
+    let starts = board_names
+        .iter()
+        .map(|name| Fen::load(name).unwrap_or_else(|e| usage(&e)))
+        .collect::<Vec<_>>();
+
+    if sequential {
+        let (lower, upper) = sprt.bounds();
+        println!(
+            "SPRT elo0={} elo1={} alpha={} beta={} (llr bounds {:.2} {:.2})",
+            sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta, lower, upper
+        );
+    }
+    let (score, status) = match_test(
+        &engines[0],
+        &engines[1],
+        &starts,
+        if sequential { Some(sprt) } else { None },
+        max_games,
+        |score, _| {
+            if sequential {
+                println!("{} llr {:.2}", score, sprt.llr(score));
+            } else {
+                println!("{}", score);
+            }
+        },
+    )
+    .unwrap_or_else(|e| usage(&e));
+
+    println!(
+        "{} vs {}: {}",
+        engines[0].label(),
+        engines[1].label(),
+        score
+    );
+    if sequential {
+        match status {
+            SprtStatus::AcceptH0 => println!("H0 accepted (elo <= {})", sprt.elo0),
+            SprtStatus::AcceptH1 => println!("H1 accepted (elo >= {})", sprt.elo1),
+            SprtStatus::Continue => println!("inconclusive after {} games", score.games()),
+        }
+    }
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -0,0 +1,54 @@
+use blobwar::strategy::EngineSpec;
+use blobwar::tournament::{pairings, run, write_games, Schedule, Standings};
+
+use std::env::args;
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+
+const USAGE: &str = "usage: tournament [--schedule round-robin|gauntlet] [--rounds N] \
+[--boards board1,board2,...] [--output DIR] ENGINE ENGINE...
+boards are board files or positions like r6b/8/2x2x2/3xx3/3xx3/2x2x2/8/b6r
+engine specs look like alphabeta:depth=4 or alphabetapass:time=500,name=abp";
+
+fn main() {
+    let mut schedule = Schedule::RoundRobin;
+    let mut rounds = 1;
+    let mut boards = vec!["standard".to_owned()];
+    let mut output = PathBuf::from("tournament");
+    let mut engines = Vec::new();
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--schedule" => schedule = value().parse().unwrap_or_else(|e: String| usage(&e)),
+            "--rounds" => rounds = value().parse().unwrap_or_else(|_| usage("invalid rounds")),
+            "--boards" => boards = value().split(',').map(|b| b.to_owned()).collect(),
+            "--output" => output = PathBuf::from(value()),
+            "--help" | "-h" => usage(""),
+            spec => engines.push(spec.parse::<EngineSpec>().unwrap_or_else(|e| usage(&e))),
+        }
+    }
+    if engines.len() < 2 {
+        usage("we need at least two engines");
+    }
+
+    let games = pairings(schedule, engines.len(), &boards, rounds);
+    println!("playing {} games", games.len());
+    let results = run(&engines, games).unwrap_or_else(|e| usage(&e));
+
+    let standings = Standings::new(&engines, &results);
+    print!("{}", standings);
+    fs::create_dir_all(&output).expect("failed creating output directory");
+    fs::write(output.join("results.txt"), standings.to_string()).expect("failed writing results");
+    write_games(output.join("games"), &engines, &results).expect("failed writing games");
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
+}
@@ -0,0 +1,11 @@
+use blobwar::web::WebServer;
+
+use std::env::args;
+
+fn main() {
+    let address = args().nth(1).unwrap_or("127.0.0.1:8080".to_owned());
+    println!("serving blobwar on http://{}/", address);
+    WebServer::default()
+        .serve(address.as_str())
+        .expect("web server failed");
+}
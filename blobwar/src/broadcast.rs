@@ -0,0 +1,171 @@
+//! Live broadcast of games to spectators.
+//!
+//! `Configuration::spectated_battle` tells a `Spectator` about everything happening in a game.
+//! A `Broadcast` is a spectator sending it over the network to any number of read-only clients
+//! speaking the JSON protocol (see `protocol`). Spectators joining in the middle of a game first
+//! receive everything sent since the game started.
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::configuration::Configuration;
+use crate::notation::Fen;
+use crate::protocol::{Connection, Message};
+use crate::record::GameRecord;
+
+/// Default port for spectators.
+pub const DEFAULT_BROADCAST_PORT: u16 = 12_346;
+
+/// Spectators lagging more than this are dropped rather than slowing the game down.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Watches a game.
+///
+/// All methods receive the record of the game so far.
+pub trait Spectator {
+    /// The game starts from given configuration.
+    fn game_started(&mut self, _record: &GameRecord, _start: &Configuration) {}
+    /// The last move of the record was just played.
+    fn move_played(&mut self, _record: &GameRecord) {}
+    /// Moves were taken back, the record only keeps the remaining ones.
+    fn moves_taken_back(&mut self, _record: &GameRecord) {}
+    /// The game is over, result (and termination) are set.
+    fn game_over(&mut self, _record: &GameRecord) {}
+}
+
+/// Nobody watching.
+impl Spectator for () {}
+
+/// Maybe somebody watching.
+impl<S: Spectator> Spectator for Option<S> {
+    fn game_started(&mut self, record: &GameRecord, start: &Configuration) {
+        if let Some(spectator) = self {
+            spectator.game_started(record, start)
+        }
+    }
+    fn move_played(&mut self, record: &GameRecord) {
+        if let Some(spectator) = self {
+            spectator.move_played(record)
+        }
+    }
+    fn moves_taken_back(&mut self, record: &GameRecord) {
+        if let Some(spectator) = self {
+            spectator.moves_taken_back(record)
+        }
+    }
+    fn game_over(&mut self, record: &GameRecord) {
+        if let Some(spectator) = self {
+            spectator.game_over(record)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    spectators: Vec<Connection>,
+    /// Messages sent since the current game started.
+    game: Vec<Message>,
+}
+
+/// Send games to spectators connected over TCP.
+#[derive(Clone)]
+pub struct Broadcast {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Broadcast {
+    /// Accept spectators on given address (in a background thread).
+    pub fn listen<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let broadcast = Broadcast {
+            shared: shared.clone(),
+        };
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    if let Err(e) = stream.and_then(|stream| join(&shared, stream)) {
+                        eprintln!("broadcast: spectator failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(broadcast)
+    }
+
+    /// Number of spectators currently connected.
+    pub fn spectators(&self) -> usize {
+        self.shared.lock().unwrap().spectators.len()
+    }
+
+    /// Send given message to all spectators, dropping the ones failing.
+    pub fn send(&self, message: Message) {
+        let mut shared = self.shared.lock().unwrap();
+        if let Message::Header { .. } = message {
+            shared.game.clear();
+        }
+        shared
+            .spectators
+            .retain_mut(|connection| connection.send(&message).is_ok());
+        shared.game.push(message);
+    }
+}
+
+/// Greet a new spectator and bring it up to date.
+fn join(shared: &Mutex<Shared>, stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut connection = Connection::new(stream)?;
+    connection.handshake("blobwar broadcast")?;
+    let mut shared = shared.lock().unwrap();
+    for message in &shared.game {
+        connection.send(message)?;
+    }
+    shared.spectators.push(connection);
+    Ok(())
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl Spectator for Broadcast {
+    fn game_started(&mut self, record: &GameRecord, start: &Configuration) {
+        self.send(Message::Header {
+            red: record.red.clone(),
+            blue: record.blue.clone(),
+            board: Fen::from_configuration(start, 0).to_string(),
+            date: record.date.clone(),
+        })
+    }
+
+    fn move_played(&mut self, record: &GameRecord) {
+        if let Some(last) = record.moves.last() {
+            self.send(Message::Played {
+                ply: record.moves.len() - 1,
+                movement: last.movement,
+                time: last.time,
+                timestamp: timestamp(),
+            })
+        }
+    }
+
+    fn moves_taken_back(&mut self, record: &GameRecord) {
+        self.send(Message::TakeBack {
+            ply: record.moves.len(),
+        })
+    }
+
+    fn game_over(&mut self, record: &GameRecord) {
+        self.send(Message::GameOver {
+            result: record.result.unwrap_or_default(),
+            termination: record.termination.map(|t| t.to_string()),
+            position: None,
+        })
+    }
+}
@@ -1,17 +1,42 @@
 //! Provide a `Configuration` for storing game state ; a `Movement` for storing moves to play.
 use super::board::Board;
+use super::broadcast::Spectator;
 use super::positions::{BoardPosition, Position, Positions};
-use super::strategy::Strategy;
+use super::notation::Fen;
+use super::record::{GameRecord, RecordedMove, Termination};
+use super::render::{render, render_io, Markers, Style};
+use super::strategy::{Request, Strategy};
 use serde_derive::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::fmt;
+use std::io;
 use std::iter::once;
-use term;
+use std::str::FromStr;
+use std::time::Instant;
+
+thread_local! {
+    /// Number of configurations created by `play` on this thread, see `take_played`.
+    static PLAYED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Number of configurations created by `play` on the current thread since the last call,
+/// that is the nodes visited by the searches it ran.
+pub fn take_played() -> u64 {
+    PLAYED.with(|played| played.replace(0))
+}
 
 const BOARD_SIZE: usize = 8;
 const PIECE_TYPES: usize = 2;
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 /// Movements : clone some blob or jump
+///
+/// JSON keeps the derived form (`{"Jump":[0,18]}`) of the legacy protocol, the network
+/// protocol writes them as text (see `protocol`).
+///
+/// In text they use an algebraic notation : columns are `a` to `h` and rows `1` to
+/// `8`, a duplication is written with its destination (`b2`) and a jump with its source and
+/// destination (`a1-c3`).
 pub enum Movement {
     /// For copy we just need the destination
     Duplicate(u8),
@@ -19,6 +44,68 @@ pub enum Movement {
     Jump(u8, u8),
 }
 
+fn format_cell(f: &mut fmt::Formatter, position: Position) -> fmt::Result {
+    let (x, y) = position.to_2d();
+    write!(f, "{}{}", (b'a' + x) as char, y + 1)
+}
+
+fn parse_cell(string: &str) -> Result<Position, String> {
+    let mut chars = string.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(column @ 'a'..='h'), Some(row @ '1'..='8'), None) => Ok(Position::from_2d(
+            column as u8 - b'a',
+            row as u8 - b'1',
+        )),
+        _ => Err(format!("invalid cell {}", string)),
+    }
+}
+
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Movement::Duplicate(destination) => format_cell(f, destination),
+            Movement::Jump(source, destination) => {
+                format_cell(f, source)?;
+                write!(f, "-")?;
+                format_cell(f, destination)
+            }
+        }
+    }
+}
+
+impl FromStr for Movement {
+    type Err = String;
+    /// Parse a move. A duplication can also be given with its source (`a1-b2`).
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let string = string.trim().to_lowercase();
+        match string.split_once('-') {
+            None => Ok(Movement::Duplicate(parse_cell(&string)?)),
+            Some((from, to)) => {
+                let (source, destination) = (parse_cell(from)?, parse_cell(to)?);
+                match source.distance_to(destination) {
+                    1 => Ok(Movement::Duplicate(destination)),
+                    2 => Ok(Movement::Jump(source, destination)),
+                    _ => Err(format!("{} is too far from {}", to, from)),
+                }
+            }
+        }
+    }
+}
+
+/// Format a possible move, `pass` standing for no move.
+pub fn format_move(movement: &Option<Movement>) -> String {
+    movement.map_or_else(|| "pass".to_owned(), |m| m.to_string())
+}
+
+/// Parse a move or `pass`.
+pub fn parse_move(string: &str) -> Result<Option<Movement>, String> {
+    if string.trim().eq_ignore_ascii_case("pass") {
+        Ok(None)
+    } else {
+        string.parse().map(Some)
+    }
+}
+
 #[derive(Copy, Clone)]
 /// Game state. We know, who should play, what is the board and where every blob is located.
 pub struct Configuration<'a> {
@@ -41,6 +128,25 @@ impl<'a> Configuration<'a> {
         }
     }
 
+    /// Create a `Configuration` with given blobs (red then blue) on given `Board`.
+    pub(crate) fn from_blobs(board: &'a Board, blobs: [Positions; 2], current_player: bool) -> Self {
+        Configuration {
+            blobs,
+            board,
+            current_player,
+        }
+    }
+
+    /// Red and blue blobs.
+    pub(crate) fn blobs(&self) -> [Positions; 2] {
+        self.blobs
+    }
+
+    /// `Board` we are playing on.
+    pub fn board(&self) -> &'a Board {
+        self.board
+    }
+
     /// Calculates the Zobrist hash key for the current board position using the given Zobrist hash table.
     ///
     /// # Arguments
@@ -91,6 +197,7 @@ impl<'a> Configuration<'a> {
 
     /// Create a new `Configuration` by playing given `Movement` on self.
     pub fn play(&self, movement: &Movement) -> Self {
+        PLAYED.with(|played| played.set(played.get() + 1));
         let mut new_configuration = *self;
         new_configuration.apply_movement(movement);
         new_configuration
@@ -153,58 +260,185 @@ impl<'a> Configuration<'a> {
     }
 
     /// Play a match between the given players starting from current `Configuration`.
-    pub fn battle<T: Strategy, U: Strategy>(&mut self, mut player_one: T, mut player_two: U) {
+    /// Return the record of the game.
+    pub fn battle<T: Strategy, U: Strategy>(&mut self, player_one: T, player_two: U) -> GameRecord {
+        self.spectated_battle(player_one, player_two, &mut ())
+    }
+
+    /// Like `battle`, telling given `Spectator` about the game as it goes.
+    pub fn spectated_battle<T: Strategy, U: Strategy>(
+        &mut self,
+        mut player_one: T,
+        mut player_two: U,
+        spectator: &mut dyn Spectator,
+    ) -> GameRecord {
+        let mut record = GameRecord::new(self, &player_one.to_string(), &player_two.to_string());
+        spectator.game_started(&record, self);
+        player_one.game_started(self);
+        player_two.game_started(self);
+        // configurations before each recorded move, for undos
+        let mut history = Vec::new();
+        let style = Style::detect();
+        let mut markers = Markers::default();
         while !self.game_over() {
+            let side = ["red", "blue"][self.current_player as usize];
             println!(
                 "{} player's turn (he is losing by {} before playing)",
-                ["red", "blue"][self.current_player as usize],
+                side,
                 self.value()
             );
-            println!("{}", self);
-            let play_attempt = if self.current_player {
-                player_two.compute_next_move(self)
+            println!("position: {}", Fen::from_configuration(self, record.moves.len() as u32));
+            render_io(&mut io::stdout(), self, style, &markers).expect("failed writing board");
+            let start = Instant::now();
+            let (play_attempt, request) = if self.current_player {
+                (player_two.compute_next_move(self), player_two.request())
             } else {
-                player_one.compute_next_move(self)
+                (player_one.compute_next_move(self), player_one.request())
             };
+            match request {
+                Some(Request::Undo) => {
+                    // go back to the last time we had to play
+                    let player = self.current_player;
+                    match history.iter().rposition(|c: &Configuration| c.current_player == player) {
+                        Some(index) => {
+                            *self = history[index];
+                            history.truncate(index);
+                            record.moves.truncate(index);
+                            markers = Markers::default();
+                            spectator.moves_taken_back(&record);
+                            println!("{} takes back its last move", side);
+                        }
+                        None => println!("nothing to undo"),
+                    }
+                    continue;
+                }
+                Some(Request::Save(path)) => {
+                    match record.save(&path) {
+                        Ok(()) => println!("game saved into {}", path),
+                        Err(e) => println!("failed saving game into {}: {}", path, e),
+                    }
+                    continue;
+                }
+                Some(Request::Resign) => {
+                    println!("{} resigns", side);
+                    record.termination = Some(Termination::Resignation(self.current_player));
+                    break;
+                }
+                Some(Request::Forfeit(reason)) => {
+                    println!("{} forfeits ({})", side, reason);
+                    record.termination = Some(Termination::Forfeit(self.current_player, reason));
+                    break;
+                }
+                None => {}
+            }
+            let passed = record.moves.last().is_some_and(|m| m.movement.is_none());
+            history.push(*self);
+            record.moves.push(RecordedMove {
+                movement: play_attempt,
+                time: Some(start.elapsed().as_millis() as u64),
+                ..Default::default()
+            });
+            spectator.move_played(&record);
+            println!("{} plays {}", side, format_move(&play_attempt));
+            markers = Markers::new(self, &play_attempt);
             if let Some(ref next_move) = play_attempt {
                 assert!(self.check_move(next_move));
                 self.apply_movement(next_move);
             } else {
                 self.current_player = !self.current_player;
+                // nobody can move anymore
+                if passed {
+                    break;
+                }
             }
         }
 
-        let value = self.blobs[0].len() - self.blobs[1].len();
-        match value {
-            x if x > 0 => println!("RED ({}) wins over BLUE ({})!", player_one, player_two),
-            x if x < 0 => println!("BLUE ({}) wins over RED ({})!", player_two, player_one),
-            _ => println!("DRAW!"),
+        let value = self.red_value();
+        record.result = Some(value);
+        player_one.game_over(self, record.termination.as_ref());
+        player_two.game_over(self, record.termination.as_ref());
+        spectator.game_over(&record);
+        match record.winner() {
+            Some(false) => println!("RED ({}) wins over BLUE ({})!", player_one, player_two),
+            Some(true) => println!("BLUE ({}) wins over RED ({})!", player_two, player_one),
+            None => println!("DRAW!"),
         }
-        println!("{}", self);
+        render_io(&mut io::stdout(), self, style, &markers).expect("failed writing board");
         println!("GAME OVER (red value of {})", value);
+        record
     }
 
-    /// Play a match between the given players starting from current `Configuration`.
-    pub fn battle_no_log<T: Strategy, U: Strategy>(&mut self, mut player_one: T, mut player_two: U) {
+    /// Like `battle`, printing nothing : for games played in the background.
+    /// Players resigning or forfeiting end the game, other requests are ignored.
+    pub fn quiet_battle<T: Strategy, U: Strategy>(
+        &mut self,
+        mut player_one: T,
+        mut player_two: U,
+    ) -> GameRecord {
+        let mut record = GameRecord::new(self, &player_one.to_string(), &player_two.to_string());
+        player_one.game_started(self);
+        player_two.game_started(self);
         while !self.game_over() {
-            let play_attempt = if self.current_player {
-                player_two.compute_next_move(self)
+            let start = Instant::now();
+            let (play_attempt, request) = if self.current_player {
+                (player_two.compute_next_move(self), player_two.request())
             } else {
-                player_one.compute_next_move(self)
+                (player_one.compute_next_move(self), player_one.request())
             };
+            match request {
+                Some(Request::Resign) => {
+                    record.termination = Some(Termination::Resignation(self.current_player));
+                    break;
+                }
+                Some(Request::Forfeit(reason)) => {
+                    record.termination = Some(Termination::Forfeit(self.current_player, reason));
+                    break;
+                }
+                _ => {}
+            }
+            let passed = record.moves.last().is_some_and(|m| m.movement.is_none());
+            record.moves.push(RecordedMove {
+                movement: play_attempt,
+                time: Some(start.elapsed().as_millis() as u64),
+                ..Default::default()
+            });
             if let Some(ref next_move) = play_attempt {
                 assert!(self.check_move(next_move));
                 self.apply_movement(next_move);
             } else {
                 self.current_player = !self.current_player;
+                // nobody can move anymore
+                if passed {
+                    break;
+                }
             }
         }
 
-        let value = self.blobs[0].len() - self.blobs[1].len();
+        record.result = Some(self.red_value());
+        player_one.game_over(self, record.termination.as_ref());
+        player_two.game_over(self, record.termination.as_ref());
+        record
+    }
+
+    /// Play a match between the given players starting from current `Configuration`.
+    /// Return the final red value (#red - #blue), or `i8::MAX` against a player who resigned or
+    /// forfeited (`-i8::MAX` against red).
+    /// The game also stops if both players pass in a row.
+    pub fn battle_no_log<T: Strategy, U: Strategy>(&mut self, player_one: T, player_two: U) -> i8 {
+        match self.quiet_battle(player_one, player_two).termination {
+            Some(termination) if termination.loser() => i8::MAX,
+            Some(_) => -i8::MAX,
+            None => self.red_value(),
+        }
+    }
+
+    /// Return the value for the red player (#red - #blue) whoever plays next.
+    pub fn red_value(&self) -> i8 {
+        self.blobs[0].len() - self.blobs[1].len()
     }
 
     /// Return true if no empty space remains or someone died.
-    fn game_over(&self) -> bool {
+    pub fn game_over(&self) -> bool {
         self.blobs[0].is_empty()
             || self.blobs[1].is_empty()
             || self.blobs[0]
@@ -297,39 +531,6 @@ impl<'a> Configuration<'a> {
 
 impl<'a> fmt::Display for Configuration<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "\n  01234567 \n")?;
-        write!(f, " +--------+\n")?;
-        for (index, (hole, (red, blue))) in self
-            .board
-            .holes
-            .full_bits()
-            .zip(self.blobs[0].full_bits().zip(self.blobs[1].full_bits()))
-            .enumerate()
-        {
-            if index % 8 == 0 {
-                write!(f, "{}|", index / 8)?;
-            }
-            let mut t = term::stdout().unwrap();
-            match (hole, red, blue) {
-                (true, false, false) => write!(f, "x")?,
-                (false, true, false) => {
-                    t.fg(term::color::RED).unwrap();
-                    write!(f, "x")?;
-                    t.reset().unwrap();
-                }
-                (false, false, true) => {
-                    t.fg(term::color::CYAN).unwrap();
-                    write!(f, "o")?;
-                    t.reset().unwrap();
-                }
-                (false, false, false) => write!(f, " ")?,
-                _ => panic!("invalid board: {} {} {}", hole, red, blue),
-            }
-            if index % 8 == 7 {
-                write!(f, "|\n")?;
-            }
-        }
-        write!(f, " +--------+")?;
-        Ok(())
+        render(f, self, Style::Plain, &Markers::default())
     }
 }
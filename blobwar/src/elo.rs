@@ -0,0 +1,331 @@
+//! Elo estimation and sequential probability ratio test (SPRT) between two engines.
+//!
+//! Games are played by pairs on the same board with colours swapped, using
+//! `Configuration::battle_no_log`. After each batch of pairs the SPRT log-likelihood ratio
+//! is updated and the match stops as soon as one hypothesis is accepted.
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::notation::Fen;
+use crate::strategy::EngineSpec;
+
+/// Convert an expected score (between 0 and 1) into an Elo difference.
+/// Scores of 0 and 1 give infinite differences, see `Score::elo` for bounded estimates.
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Convert an Elo difference into an expected score.
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+/// Wins, draws and losses from the point of view of the first engine.
+pub struct Score {
+    /// Games won.
+    pub wins: usize,
+    /// Games drawn.
+    pub draws: usize,
+    /// Games lost.
+    pub losses: usize,
+}
+
+impl Score {
+    /// Record a game given its final value for the first engine.
+    pub fn add(&mut self, value: i8) {
+        match value {
+            x if x > 0 => self.wins += 1,
+            x if x < 0 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    /// Number of games played.
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Mean score per game (1 for a win, 0.5 for a draw).
+    pub fn mean(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Mean score kept half a game away from 0 and 1 : a clean sweep is a normal result of a short
+    /// match, not an infinite Elo difference.
+    fn bounded_mean(&self) -> f64 {
+        let margin = 0.5 / self.games() as f64;
+        self.mean().max(margin).min(1.0 - margin)
+    }
+
+    /// Variance of the score of one game.
+    pub fn variance(&self) -> f64 {
+        let games = self.games() as f64;
+        let mean = self.mean();
+        (self.wins as f64 * (1.0 - mean).powi(2)
+            + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2))
+            / games
+    }
+
+    /// Variance of the score of one game, never zero.
+    /// Engines are deterministic so all games may end the same way : when the variance
+    /// is zero we estimate it with one more win and one more loss.
+    fn smoothed_variance(&self) -> f64 {
+        let variance = self.variance();
+        if variance != 0.0 {
+            return variance;
+        }
+        let mut smoothed = *self;
+        smoothed.wins += 1;
+        smoothed.losses += 1;
+        smoothed.variance()
+    }
+
+    /// Estimated Elo difference (0 without any game).
+    pub fn elo(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        elo_from_score(self.bounded_mean())
+    }
+
+    /// Half width of the 95% confidence interval on the Elo difference
+    /// (first order approximation around the mean score, 0 without any game).
+    pub fn elo_error(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let deviation = (self.smoothed_variance() / self.games() as f64).sqrt();
+        let mean = self.bounded_mean();
+        1.96 * deviation * 400.0 / (std::f64::consts::LN_10 * mean * (1.0 - mean))
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} games (+{} ={} -{}) elo {:+.1} +/- {:.1}",
+            self.games(),
+            self.wins,
+            self.draws,
+            self.losses,
+            self.elo(),
+            self.elo_error()
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Current state of a sequential test.
+pub enum SprtStatus {
+    /// Not enough data yet.
+    Continue,
+    /// Elo difference is likely below elo0.
+    AcceptH0,
+    /// Elo difference is likely above elo1.
+    AcceptH1,
+}
+
+#[derive(Copy, Clone, Debug)]
+/// Sequential probability ratio test between H0: elo = elo0 and H1: elo = elo1.
+pub struct Sprt {
+    /// Elo difference of the null hypothesis.
+    pub elo0: f64,
+    /// Elo difference of the alternative hypothesis.
+    pub elo1: f64,
+    /// Probability of accepting H1 while H0 holds.
+    pub alpha: f64,
+    /// Probability of accepting H0 while H1 holds.
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// Lower and upper bounds on the log-likelihood ratio.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of given score (normal approximation).
+    pub fn llr(&self, score: &Score) -> f64 {
+        if score.games() == 0 {
+            return 0.0;
+        }
+        let variance = score.smoothed_variance();
+        let (s0, s1) = (score_from_elo(self.elo0), score_from_elo(self.elo1));
+        score.games() as f64 * (s1 - s0) * (2.0 * score.mean() - s0 - s1) / (2.0 * variance)
+    }
+
+    /// Decide on given score.
+    pub fn status(&self, score: &Score) -> SprtStatus {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtStatus::AcceptH1
+        } else if llr <= lower {
+            SprtStatus::AcceptH0
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+/// Play two games from given position, colours swapped.
+/// Return both final values from the point of view of the first engine.
+pub fn play_pair(start: &Fen, first: &EngineSpec, second: &EngineSpec) -> Result<[i8; 2], String> {
+    Ok([play(start, first, second)?, -play(start, second, first)?])
+}
+
+/// Play one game from given position, return its final red value.
+fn play(start: &Fen, red: &EngineSpec, blue: &EngineSpec) -> Result<i8, String> {
+    let board = start.board();
+    Ok(start
+        .configuration(&board)
+        .battle_no_log(red.build()?, blue.build()?))
+}
+
+/// Play pairs of games between two engines, cycling through starting positions, until `max_games` are played
+/// or the sequential test (if any) decides.
+/// `progress` is called after each batch of pairs.
+pub fn match_test<F: FnMut(&Score, SprtStatus)>(
+    first: &EngineSpec,
+    second: &EngineSpec,
+    starts: &[Fen],
+    sprt: Option<Sprt>,
+    max_games: usize,
+    mut progress: F,
+) -> Result<(Score, SprtStatus), String> {
+    if starts.is_empty() {
+        return Err("no starting position".to_owned());
+    }
+    let mut score = Score::default();
+    let mut status = SprtStatus::Continue;
+    // timed engines get the whole machine for their time
+    let batch = if first.time.is_some() || second.time.is_some() {
+        1
+    } else {
+        rayon::current_num_threads()
+    };
+    let mut pairs = 0;
+    while score.games() < max_games && status == SprtStatus::Continue {
+        let remaining = max_games - score.games();
+        let count = batch.min(remaining.div_ceil(2));
+        let results = (0..count)
+            .into_par_iter()
+            .map(|index| {
+                let start = &starts[(pairs + index) % starts.len()];
+                // an odd number of games ends with a lone game
+                if 2 * index + 1 == remaining {
+                    Ok(vec![play(start, first, second)?])
+                } else {
+                    play_pair(start, first, second).map(|values| values.to_vec())
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        pairs += count;
+        for value in results.into_iter().flatten() {
+            score.add(value);
+        }
+        if let Some(sprt) = sprt {
+            status = sprt.status(&score);
+        }
+        progress(&score, status);
+    }
+    Ok((score, status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(wins: usize, draws: usize, losses: usize) -> Score {
+        Score {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn elo_and_score_are_inverse() {
+        assert_eq!(elo_from_score(0.5), 0.0);
+        assert!(close(elo_from_score(10.0 / 11.0), 400.0));
+        assert!(close(elo_from_score(1.0 / 11.0), -400.0));
+        for &elo in &[-300.0, -10.0, 0.0, 42.0, 700.0] {
+            assert!(close(elo_from_score(score_from_elo(elo)), elo));
+        }
+    }
+
+    #[test]
+    fn sweeps_are_bounded() {
+        let sweep = score(10, 0, 0);
+        assert!(close(sweep.elo(), elo_from_score(0.95)));
+        assert!(close(score(0, 0, 10).elo(), -sweep.elo()));
+        assert!(sweep.elo_error().is_finite() && sweep.elo_error() > 0.0);
+        assert_eq!(score(1, 0, 0).elo(), 0.0);
+        assert_eq!(Score::default().elo(), 0.0);
+        assert_eq!(Score::default().elo_error(), 0.0);
+    }
+
+    #[test]
+    fn error_shrinks_with_games() {
+        let few = score(6, 2, 2);
+        let many = score(60, 20, 20);
+        assert!(close(few.elo(), many.elo()));
+        assert!(many.elo_error() < few.elo_error());
+        assert!(close(few.elo_error() / many.elo_error(), 10f64.sqrt()));
+    }
+
+    #[test]
+    fn sprt_bounds() {
+        let (lower, upper) = Sprt::default().bounds();
+        assert!(close(lower, (0.05f64 / 0.95).ln()));
+        assert!(close(upper, -lower));
+    }
+
+    #[test]
+    fn sprt_decisions() {
+        let sprt = Sprt::default();
+        assert_eq!(sprt.llr(&Score::default()), 0.0);
+        assert_eq!(sprt.status(&Score::default()), SprtStatus::Continue);
+        // right in the middle of both hypotheses
+        let middle = (score_from_elo(sprt.elo0) + score_from_elo(sprt.elo1)) / 2.0;
+        let even = score(1000, 0, (1000.0 * (1.0 - middle) / middle).round() as usize);
+        assert!(sprt.llr(&even).abs() < 0.1);
+        assert!(sprt.llr(&score(0, 10, 0)) < 0.0);
+        assert_eq!(sprt.status(&score(2, 0, 1)), SprtStatus::Continue);
+        assert_eq!(sprt.status(&score(300, 0, 100)), SprtStatus::AcceptH1);
+        assert_eq!(sprt.status(&score(100, 0, 300)), SprtStatus::AcceptH0);
+        assert_eq!(sprt.status(&score(0, 1000, 0)), SprtStatus::AcceptH0);
+    }
+
+    #[test]
+    fn matches_play_exactly_the_games_asked() {
+        let greedy: EngineSpec = "greedy".parse().unwrap();
+        let starts = ["r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap()];
+        let (score, status) = match_test(&greedy, &greedy, &starts, None, 3, |_, _| {}).unwrap();
+        assert_eq!(score.games(), 3);
+        assert_eq!(status, SprtStatus::Continue);
+        assert!(match_test(&greedy, &greedy, &[], None, 2, |_, _| {}).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+//! Text protocol between a controller and an engine, in the spirit of UCI.
+//!
+//! The engine reads commands on its stdin (or a Unix-domain socket) and writes replies on its
+//! stdout, one per line (`>` for commands, `<` for replies) :
+//! ```text
+//! > blobwar
+//! < id name alphabeta:depth=4
+//! < option name strategy
+//! < option name depth
+//! < option name time
+//! < blobwarok
+//! > setoption name strategy value alphabetatable
+//! > isready
+//! < readyok
+//! > newgame
+//! > position r6b/8/8/8/8/8/8/b6r r 0
+//! > go depth 5
+//! < bestmove b2
+//! > go movetime 500
+//! > stop
+//! < info depth 3 score 2
+//! < bestmove a1-c3
+//! > quit
+//! ```
+//! `go` searches with the engine's own depth or time, `go depth N` at fixed depth,
+//! `go movetime MS` for at most `MS` milliseconds and `go infinite` until `stop`. Each `go` is
+//! answered by exactly one `bestmove` (possibly `pass`), which `stop` hurries. Other options given
+//! by `setoption` are kept for the engine factory (see `EngineSpec`). Engines may send
+//! `info` lines at any time, controllers ignore them.
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::configuration::{format_move, parse_move, Movement};
+use crate::notation::Fen;
+use crate::strategy::registry::SEARCH_ENGINES;
+use crate::strategy::{EngineSpec, Registry, Search};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// How long to search.
+pub enum Limit {
+    /// Fixed depth search.
+    Depth(u8),
+    /// Search at most this many milliseconds.
+    MoveTime(u64),
+    /// Search until told to stop.
+    Infinite,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Commands sent to engines.
+pub enum Command {
+    /// Start of the conversation, answered by `id`, `option`s and `blobwarok`.
+    Blobwar,
+    /// Answered by `readyok` once the engine is done with previous commands.
+    IsReady,
+    /// Change one of the engine's options.
+    SetOption {
+        /// Option name.
+        name: String,
+        /// New value.
+        value: String,
+    },
+    /// Next positions belong to a new game.
+    NewGame,
+    /// Position to search next.
+    Position(Fen),
+    /// Search the position, the engine's own limit if none is given.
+    Go(Option<Limit>),
+    /// Answer `bestmove` now.
+    Stop,
+    /// Exit.
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Replies of engines.
+pub enum Reply {
+    /// Engine name.
+    Id(String),
+    /// Name of an option the engine understands.
+    Option(String),
+    /// End of the handshake.
+    BlobwarOk,
+    /// Answer to `isready`.
+    ReadyOk,
+    /// Anything the engine wants to tell (`info string ...`).
+    Info(String),
+    /// Move chosen after a `go`.
+    BestMove(Option<Movement>),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Depth(depth) => write!(f, "depth {}", depth),
+            Limit::MoveTime(time) => write!(f, "movetime {}", time),
+            Limit::Infinite => write!(f, "infinite"),
+        }
+    }
+}
+
+impl FromStr for Limit {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = string.split_whitespace().collect();
+        match words.as_slice() {
+            ["depth", depth] => depth
+                .parse()
+                .map(Limit::Depth)
+                .map_err(|_| format!("invalid depth {}", depth)),
+            ["movetime", time] => time
+                .parse()
+                .map(Limit::MoveTime)
+                .map_err(|_| format!("invalid time {}", time)),
+            ["infinite"] => Ok(Limit::Infinite),
+            _ => Err(format!("invalid limit {}", string)),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Blobwar => write!(f, "blobwar"),
+            Command::IsReady => write!(f, "isready"),
+            Command::SetOption { name, value } => {
+                write!(f, "setoption name {} value {}", name, value)
+            }
+            Command::NewGame => write!(f, "newgame"),
+            Command::Position(position) => write!(f, "position {}", position),
+            Command::Go(None) => write!(f, "go"),
+            Command::Go(Some(limit)) => write!(f, "go {}", limit),
+            Command::Stop => write!(f, "stop"),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+/// Split a line into its first word and the rest.
+fn keyword(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    line.split_once(char::is_whitespace)
+        .map_or((line, ""), |(keyword, rest)| (keyword, rest.trim()))
+}
+
+impl FromStr for Command {
+    type Err = String;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        Ok(match keyword(line) {
+            ("blobwar", _) => Command::Blobwar,
+            ("isready", _) => Command::IsReady,
+            ("setoption", rest) => {
+                let (name, value) = rest
+                    .strip_prefix("name ")
+                    .and_then(|option| option.split_once(" value "))
+                    .ok_or_else(|| format!("expected name and value, got {}", rest))?;
+                Command::SetOption {
+                    name: name.trim().to_owned(),
+                    value: value.trim().to_owned(),
+                }
+            }
+            ("newgame", _) => Command::NewGame,
+            ("position", position) => Command::Position(position.parse()?),
+            ("go", "") => Command::Go(None),
+            ("go", limit) => Command::Go(Some(limit.parse()?)),
+            ("stop", _) => Command::Stop,
+            ("quit", _) => Command::Quit,
+            (other, _) => return Err(format!("unknown command {}", other)),
+        })
+    }
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reply::Id(name) => write!(f, "id name {}", name),
+            Reply::Option(name) => write!(f, "option name {}", name),
+            Reply::BlobwarOk => write!(f, "blobwarok"),
+            Reply::ReadyOk => write!(f, "readyok"),
+            Reply::Info(info) => write!(f, "info {}", info),
+            Reply::BestMove(movement) => write!(f, "bestmove {}", format_move(movement)),
+        }
+    }
+}
+
+impl FromStr for Reply {
+    type Err = String;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        Ok(match keyword(line) {
+            ("id", rest) => Reply::Id(
+                rest.strip_prefix("name ")
+                    .ok_or_else(|| format!("invalid id {}", rest))?
+                    .to_owned(),
+            ),
+            ("option", rest) => Reply::Option(
+                rest.strip_prefix("name ")
+                    .ok_or_else(|| format!("invalid option {}", rest))?
+                    .to_owned(),
+            ),
+            ("blobwarok", _) => Reply::BlobwarOk,
+            ("readyok", _) => Reply::ReadyOk,
+            ("info", info) => Reply::Info(info.to_owned()),
+            ("bestmove", movement) => Reply::BestMove(parse_move(movement)?),
+            (other, _) => return Err(format!("unknown reply {}", other)),
+        })
+    }
+}
+
+/// What the engine loop waits for.
+enum Event {
+    Command(Command),
+    Invalid(String),
+    /// A search thread is done.
+    Done(Option<Movement>),
+}
+
+/// Search in progress.
+enum Running {
+    /// Fixed depth search (or engine without anytime search) in a thread, sending `Done`.
+    Thread,
+    /// Anytime search, stopped on `stop` or once past the deadline.
+    Anytime(Search, Option<Instant>),
+}
+
+/// Speak the engine protocol on given input and output, with the strategy of `spec`.
+/// Returns on `quit` or once the input is closed.
+pub fn serve<R, W>(mut spec: EngineSpec, input: R, mut output: W) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    let (events, received) = channel();
+    let commands = events.clone();
+    thread::spawn(move || {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let event = line.parse().map_or_else(Event::Invalid, Event::Command);
+            if commands.send(event).is_err() {
+                return;
+            }
+        }
+        let _ = commands.send(Event::Command(Command::Quit));
+    });
+
+    let mut reply = |reply: Reply| -> io::Result<()> {
+        writeln!(output, "{}", reply)?;
+        output.flush()
+    };
+    let info = |message: String| Reply::Info(format!("string {}", message));
+    let registry = Registry::default();
+    let mut position: Option<Fen> = None;
+    let mut running: Option<Running> = None;
+    loop {
+        let event = match &running {
+            Some(Running::Anytime(_, Some(deadline))) => {
+                match received.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => Event::Command(Command::Stop),
+                    Err(RecvTimeoutError::Disconnected) => Event::Command(Command::Quit),
+                }
+            }
+            _ => received.recv().unwrap_or(Event::Command(Command::Quit)),
+        };
+        let command = match event {
+            Event::Command(command) => command,
+            Event::Invalid(error) => {
+                reply(info(error))?;
+                continue;
+            }
+            Event::Done(movement) => {
+                running = None;
+                reply(Reply::BestMove(movement))?;
+                continue;
+            }
+        };
+        match command {
+            Command::Blobwar => {
+                reply(Reply::Id(spec.label()))?;
+                for option in &["strategy", "depth", "time"] {
+                    reply(Reply::Option((*option).to_owned()))?;
+                }
+                reply(Reply::BlobwarOk)?;
+            }
+            Command::IsReady => reply(Reply::ReadyOk)?,
+            Command::SetOption { name, value } => {
+                if let Err(e) = set_option(&mut spec, &name, &value) {
+                    reply(info(e))?;
+                }
+            }
+            Command::NewGame => position = None,
+            Command::Position(fen) => position = Some(fen),
+            Command::Go(_) if running.is_some() => reply(info("already searching".to_owned()))?,
+            Command::Go(limit) => {
+                let fen = match position {
+                    Some(fen) => fen,
+                    None => {
+                        reply(info("no position to search".to_owned()))?;
+                        reply(Reply::BestMove(None))?;
+                        continue;
+                    }
+                };
+                let limit = limit.or_else(|| spec.time.map(Limit::MoveTime));
+                running = Some(
+                    go(&spec, &registry, fen, limit, &events).unwrap_or_else(|e| {
+                        let _ = reply(info(e));
+                        search_in_thread(&spec, None, fen, &events)
+                    }),
+                );
+            }
+            Command::Stop => {
+                // threads cannot be interrupted, they answer once done
+                if let Some(Running::Anytime(search, _)) = running.take() {
+                    let progress = search.stop();
+                    if let Some(progress) = progress {
+                        reply(Reply::Info(match progress.score {
+                            Some(score) => format!("depth {} score {}", progress.depth, score),
+                            None => format!("depth {}", progress.depth),
+                        }))?;
+                    }
+                    reply(Reply::BestMove(progress.and_then(|p| p.movement)))?;
+                }
+            }
+            Command::Quit => {
+                if let Some(Running::Anytime(search, _)) = running.take() {
+                    search.stop();
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Change an option of `spec`.
+fn set_option(spec: &mut EngineSpec, name: &str, value: &str) -> Result<(), String> {
+    match name.to_lowercase().as_str() {
+        "strategy" => {
+            if !SEARCH_ENGINES.contains(&value) {
+                return Err(format!("{} is not a search engine", value));
+            }
+            spec.strategy = value.to_owned();
+        }
+        "depth" => {
+            spec.depth = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid depth {}", value))?,
+            );
+            spec.time = None;
+        }
+        "time" => {
+            spec.time = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid time {}", value))?,
+            );
+            spec.depth = None;
+        }
+        other => {
+            spec.options.insert(other.to_owned(), value.to_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Start searching `fen`. Anytime searches are used for time limits when the engine has one.
+fn go(
+    spec: &EngineSpec,
+    registry: &Registry,
+    fen: Fen,
+    limit: Option<Limit>,
+    events: &Sender<Event>,
+) -> Result<Running, String> {
+    let timed = match limit {
+        Some(Limit::MoveTime(time)) => Some(Some(Instant::now() + Duration::from_millis(time))),
+        Some(Limit::Infinite) => Some(None),
+        _ => None,
+    };
+    match timed {
+        Some(deadline) if registry.anytime(&spec.strategy).is_some() => {
+            let board = fen.board();
+            let search = Search::start(&spec.strategy, &fen.configuration(&board))
+                .map_err(|e| format!("failed starting search: {}", e))?;
+            Ok(Running::Anytime(search, deadline))
+        }
+        _ => {
+            let depth = match limit {
+                Some(Limit::Depth(depth)) => Some(depth),
+                _ => spec.depth,
+            };
+            Ok(search_in_thread(spec, depth, fen, events))
+        }
+    }
+}
+
+/// Play `fen` with the strategy of `spec` at given depth in a thread.
+fn search_in_thread(
+    spec: &EngineSpec,
+    depth: Option<u8>,
+    fen: Fen,
+    events: &Sender<Event>,
+) -> Running {
+    let mut spec = spec.clone();
+    spec.depth = depth;
+    spec.time = None;
+    let events = events.clone();
+    thread::spawn(move || {
+        let board = fen.board();
+        let configuration = fen.configuration(&board);
+        // strategies without depth refuse one
+        let movement = Registry::default()
+            .build(&spec)
+            .or_else(|_| {
+                spec.depth = None;
+                Registry::default().build(&spec)
+            })
+            .map(|mut strategy| strategy.compute_next_move(&configuration))
+            .unwrap_or(None);
+        let _ = events.send(Event::Done(movement));
+    });
+    Running::Thread
+}
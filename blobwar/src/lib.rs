@@ -2,8 +2,32 @@
 #![deny(missing_docs)]
 #![warn(clippy::all)]
 
+pub mod analysis;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod asynchronous;
 pub mod board;
+pub mod broadcast;
 pub mod configuration;
+pub mod elo;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod engine;
+pub mod lobby;
+pub mod notation;
+pub mod png;
 pub(crate) mod positions;
+pub mod protocol;
+pub mod record;
+pub mod render;
+pub mod server;
+pub mod session;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod shmem;
+#[cfg(target_arch = "wasm32")]
+#[path = "shmem_wasm.rs"]
 pub(crate) mod shmem;
 pub mod strategy;
+pub mod svg;
+pub mod tournament;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
+pub mod web;
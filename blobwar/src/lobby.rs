@@ -0,0 +1,385 @@
+//! Lobby server : many clients, many simultaneous games and a leaderboard.
+//!
+//! Clients connect and say `hello`. Between games they can list open games (`list_games`),
+//! open one (`create_game`), join one (`join_game`), ask to be paired with the next client
+//! asking for it (`pair`) or get the leaderboard (`get_leaderboard`). Each game runs on its own
+//! thread and both clients go back to the lobby once it is over. Names identify players on the
+//! leaderboard : a name is refused while another client has it.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{TcpListener, ToSocketAddrs};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::elo::Score;
+use crate::notation::Fen;
+use crate::protocol::{Connection, LeaderboardEntry, Message, OpenGame};
+use crate::server::{Clocked, Player};
+use crate::strategy::NetworkPlayer;
+
+#[derive(Clone, Debug)]
+/// Lobby settings.
+pub struct LobbySettings {
+    /// Board (file or position) used when clients do not choose one.
+    pub board: String,
+    /// Thinking time of each player for each game.
+    pub clock: Option<Duration>,
+    /// Directory where game records are saved.
+    pub records: Option<PathBuf>,
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        LobbySettings {
+            board: "standard".to_owned(),
+            clock: None,
+            records: None,
+        }
+    }
+}
+
+/// Names of the connected clients.
+#[derive(Clone, Default)]
+pub(crate) struct Names(Arc<Mutex<HashSet<String>>>);
+
+impl Names {
+    /// Take given name until the returned `Name` is dropped, `None` if a client has it already.
+    pub(crate) fn take(&self, name: String) -> Option<Name> {
+        if !self.0.lock().unwrap().insert(name.clone()) {
+            return None;
+        }
+        Some(Name {
+            name,
+            names: self.clone(),
+        })
+    }
+}
+
+/// Name of a connected client, given back when the client leaves.
+pub(crate) struct Name {
+    name: String,
+    names: Names,
+}
+
+impl Deref for Name {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Drop for Name {
+    fn drop(&mut self) {
+        self.names.0.lock().unwrap().remove(&self.name);
+    }
+}
+
+/// A client waiting for an opponent.
+struct Waiting {
+    name: Name,
+    connection: Connection,
+    start: Fen,
+}
+
+#[derive(Default)]
+struct State {
+    /// Games opened with `create_game`.
+    open: BTreeMap<usize, Waiting>,
+    /// Client waiting after a `pair`.
+    pairing: Option<Waiting>,
+    next_id: usize,
+    /// Games played so far.
+    played: usize,
+    scores: HashMap<String, Score>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Results of all players, best first (a win is worth a point, a draw half a point).
+pub struct Leaderboard(pub Vec<LeaderboardEntry>);
+
+impl fmt::Display for Leaderboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<4}{:<30}{:>7}{:>7}{:>7}{:>7}{:>8}",
+            "#", "player", "games", "wins", "draws", "losses", "points"
+        )?;
+        for (rank, entry) in self.0.iter().enumerate() {
+            writeln!(
+                f,
+                "{:<4}{:<30}{:>7}{:>7}{:>7}{:>7}{:>8.1}",
+                rank + 1,
+                entry.name,
+                entry.wins + entry.draws + entry.losses,
+                entry.wins,
+                entry.draws,
+                entry.losses,
+                points(entry)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Leaderboard {
+    /// Leaderboard of given scores (by player name).
+    pub(crate) fn new(scores: &HashMap<String, Score>) -> Self {
+        let mut entries: Vec<LeaderboardEntry> = scores
+            .iter()
+            .map(|(name, score)| LeaderboardEntry {
+                name: name.clone(),
+                wins: score.wins,
+                draws: score.draws,
+                losses: score.losses,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            points(b)
+                .partial_cmp(&points(a))
+                .unwrap()
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Leaderboard(entries)
+    }
+}
+
+fn points(entry: &LeaderboardEntry) -> f64 {
+    entry.wins as f64 + entry.draws as f64 / 2.0
+}
+
+/// The lobby server.
+#[derive(Clone)]
+pub struct Lobby {
+    settings: Arc<LobbySettings>,
+    state: Arc<Mutex<State>>,
+    names: Names,
+}
+
+impl Lobby {
+    /// New empty lobby.
+    pub fn new(settings: LobbySettings) -> Self {
+        Lobby {
+            settings: Arc::new(settings),
+            state: Arc::new(Mutex::new(State::default())),
+            names: Names::default(),
+        }
+    }
+
+    /// Current leaderboard.
+    pub fn leaderboard(&self) -> Leaderboard {
+        Leaderboard::new(&self.state.lock().unwrap().scores)
+    }
+
+    /// Accept clients on given address forever.
+    pub fn serve<A: ToSocketAddrs>(self, address: A) -> io::Result<()> {
+        self.serve_listener(TcpListener::bind(address)?)
+    }
+
+    /// Accept clients coming to given listener forever.
+    pub fn serve_listener(self, listener: TcpListener) -> io::Result<()> {
+        if let Some(directory) = &self.settings.records {
+            fs::create_dir_all(directory)?;
+        }
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("lobby: accept failed: {}", e);
+                    continue;
+                }
+            };
+            let lobby = self.clone();
+            thread::spawn(move || {
+                let origin = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_owned(), |a| a.to_string());
+                let result = Connection::new(stream).and_then(|mut connection| {
+                    let name = connection.handshake("blobwar lobby")?;
+                    match lobby.names.take(name.clone()) {
+                        Some(name) => {
+                            println!("{} joined from {}", name, origin);
+                            lobby.welcome(name, connection);
+                        }
+                        None => connection.send(&Message::Error {
+                            message: format!("{} is already playing here", name),
+                        })?,
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    eprintln!("lobby: client {} failed: {}", origin, e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Answer lobby requests until the client is waiting for or playing a game.
+    fn welcome(&self, name: Name, mut connection: Connection) {
+        loop {
+            let message = match connection.receive() {
+                Ok(message) => message,
+                Err(e) => {
+                    println!("{} left ({})", name, e);
+                    return;
+                }
+            };
+            let answer = match message {
+                Message::ListGames => Message::Games {
+                    games: self
+                        .state
+                        .lock()
+                        .unwrap()
+                        .open
+                        .iter()
+                        .map(|(&id, waiting)| OpenGame {
+                            id,
+                            host: waiting.name.to_string(),
+                            board: waiting.start.to_string(),
+                        })
+                        .collect(),
+                },
+                Message::GetLeaderboard => Message::Leaderboard {
+                    entries: self.leaderboard().0,
+                },
+                Message::CreateGame { board } => {
+                    match Fen::load(board.as_deref().unwrap_or(&self.settings.board)) {
+                        Ok(start) => {
+                            let mut state = self.state.lock().unwrap();
+                            let id = state.next_id;
+                            state.next_id += 1;
+                            if connection.send(&Message::GameCreated { id }).is_ok() {
+                                state.open.insert(
+                                    id,
+                                    Waiting {
+                                        name,
+                                        connection,
+                                        start,
+                                    },
+                                );
+                            }
+                            return;
+                        }
+                        Err(e) => Message::Error { message: e },
+                    }
+                }
+                Message::JoinGame { id } => {
+                    let host = self.state.lock().unwrap().open.remove(&id);
+                    match host {
+                        Some(host) => {
+                            self.start_game(host, name, connection);
+                            return;
+                        }
+                        None => Message::Error {
+                            message: format!("no open game {}", id),
+                        },
+                    }
+                }
+                Message::Pair => {
+                    let mut state = self.state.lock().unwrap();
+                    match state.pairing.take() {
+                        Some(host) => {
+                            drop(state);
+                            self.start_game(host, name, connection);
+                        }
+                        None => match Fen::load(&self.settings.board) {
+                            Ok(start) => {
+                                state.pairing = Some(Waiting {
+                                    name,
+                                    connection,
+                                    start,
+                                })
+                            }
+                            Err(e) => eprintln!("lobby: {}", e),
+                        },
+                    }
+                    return;
+                }
+                other => Message::Error {
+                    message: format!("unexpected message {:?}", other),
+                },
+            };
+            if connection.send(&answer).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Play a game on its own thread, the host being red, then send both clients back to
+    /// the lobby.
+    fn start_game(&self, host: Waiting, name: Name, connection: Connection) {
+        let lobby = self.clone();
+        thread::spawn(move || {
+            let id = {
+                let mut state = lobby.state.lock().unwrap();
+                state.played += 1;
+                state.played
+            };
+            println!("game {} : {} against {}", id, host.name, name);
+            let remote = |name: &str, connection| {
+                Clocked::new(
+                    Player::Remote(Box::new(NetworkPlayer::from_connection(
+                        connection,
+                        name.to_owned(),
+                    ))),
+                    lobby.settings.clock,
+                )
+            };
+            let mut red = remote(&host.name, host.connection);
+            let mut blue = remote(&name, connection);
+            let board = host.start.board();
+            let mut configuration = host.start.configuration(&board);
+            let record = configuration.quiet_battle(&mut red, &mut blue);
+
+            {
+                let mut state = lobby.state.lock().unwrap();
+                let value = match record.winner() {
+                    None => 0,
+                    Some(false) => 1,
+                    Some(true) => -1,
+                };
+                state
+                    .scores
+                    .entry(host.name.to_string())
+                    .or_default()
+                    .add(value);
+                state
+                    .scores
+                    .entry(name.to_string())
+                    .or_default()
+                    .add(-value);
+            }
+            println!("game {} over\n{}", id, lobby.leaderboard());
+            if let Some(directory) = &lobby.settings.records {
+                let path = directory.join(format!("game_{:05}.txt", id));
+                if let Err(e) = record.save(&path) {
+                    eprintln!("lobby: failed saving {}: {}", path.display(), e);
+                }
+            }
+
+            for (name, player) in [(host.name, red.player), (name, blue.player)] {
+                if let Player::Remote(player) = player {
+                    // the clock may have left a read timeout
+                    if player.set_timeout(None).is_err() {
+                        continue;
+                    }
+                    if let Some(connection) = player.into_connection() {
+                        let lobby = lobby.clone();
+                        thread::spawn(move || lobby.welcome(name, connection));
+                    }
+                }
+            }
+        });
+    }
+}
@@ -1,17 +1,102 @@
-// extern crate blobwar;
-//use blobwar::board::Board;
-use blobwar::configuration::Configuration;
-use blobwar::strategy::IterativeDeepening;
-use blobwar::strategy::{
-    AlphaBeta, AlphaBetaPass, AlphaBetaTable, Greedy, Human, IterativeStrategy, MinMax, MinMaxPar
-};
+use blobwar::board::Board;
+use blobwar::broadcast::Broadcast;
+use blobwar::notation::Fen;
+use blobwar::strategy::{EngineSpec, Registry};
+
+use std::env::args;
+use std::process::exit;
+
+const USAGE: &str = "usage: blobwar [--red ENGINE] [--blue ENGINE] [--board FILE|POSITION] \
+[--start red|blue] [--games N] [--record FILE] [--broadcast PORT] [--list]
+engine specs look like alphabeta:depth=4, alphabetapass:time=500 or network:port=12345
+positions look like r6b/8/2x2x2/3xx3/3xx3/2x2x2/8/b6r (optionally followed by side and ply)";
 
 fn main() {
-    //let board = Board::load("x").expect("failed loading board");
-    let board = Default::default();
-    let mut game = Configuration::new(&board);
-    game.battle(
-        IterativeDeepening::new(IterativeStrategy::MinMax),
-        IterativeDeepening::new(IterativeStrategy::MinMaxPar),
-    );
+    let registry = Registry::default();
+    let mut red: EngineSpec = "minmax:time=1000".parse().unwrap();
+    let mut blue: EngineSpec = "minmaxpar:time=1000".parse().unwrap();
+    let mut board_name = None;
+    let mut blue_starts = None;
+    let mut games = 1;
+    let mut record_path = None;
+    let mut broadcast = None;
+
+    let mut arguments = args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().unwrap_or_else(|| usage(&argument));
+        match argument.as_str() {
+            "--red" => red = value().parse().unwrap_or_else(|e: String| usage(&e)),
+            "--blue" => blue = value().parse().unwrap_or_else(|e: String| usage(&e)),
+            "--board" => board_name = Some(value()),
+            "--start" => {
+                blue_starts = match value().as_str() {
+                    "red" => Some(false),
+                    "blue" => Some(true),
+                    other => usage(&format!("invalid starting side {}", other)),
+                }
+            }
+            "--games" => games = value().parse().unwrap_or_else(|_| usage("invalid games")),
+            "--record" => record_path = Some(value()),
+            "--broadcast" => {
+                let port: u16 = value().parse().unwrap_or_else(|_| usage("invalid port"));
+                broadcast = Some(
+                    Broadcast::listen(("0.0.0.0", port)).unwrap_or_else(|e| usage(&e.to_string())),
+                )
+            }
+            "--list" => {
+                print!("{}", registry);
+                return;
+            }
+            "--help" | "-h" => usage(""),
+            other => usage(&format!("unknown argument {}", other)),
+        }
+    }
+
+    let start = board_name
+        .map(|name| Fen::load(&name).unwrap_or_else(|e| usage(&e)))
+        .unwrap_or_else(|| Fen::from_board(&Board::default()));
+    let board = start.board();
+    let mut score = [0; 3];
+    for index in 0..games {
+        let mut game = start.configuration(&board);
+        if let Some(blue_starts) = blue_starts {
+            game.current_player = blue_starts;
+        }
+        let record = game.spectated_battle(
+            registry.build(&red).unwrap_or_else(|e| usage(&e)),
+            registry.build(&blue).unwrap_or_else(|e| usage(&e)),
+            &mut broadcast,
+        );
+        score[match record.winner() {
+            Some(false) => 2,
+            None => 1,
+            Some(true) => 0,
+        }] += 1;
+        if let Some(ref path) = record_path {
+            let path = if games > 1 {
+                format!("{}.{}", path, index + 1)
+            } else {
+                path.clone()
+            };
+            record.save(path).expect("failed saving game record");
+        }
+    }
+    if games > 1 {
+        println!(
+            "{} (red) vs {} (blue): {} won, {} drawn, {} lost",
+            red.label(),
+            blue.label(),
+            score[2],
+            score[1],
+            score[0]
+        );
+    }
+}
+
+fn usage(error: &str) -> ! {
+    if !error.is_empty() {
+        eprintln!("{}", error);
+    }
+    eprintln!("{}", USAGE);
+    exit(1)
 }
@@ -0,0 +1,220 @@
+//! Compact position notation, in the spirit of chess FEN.
+//!
+//! A position is written as `r6b/8/2x2x2/3xx3/3xx3/2x2x2/8/b6r r 0` : first the eight rows
+//! (row 1 first, column `a` first) separated by `/`, with `r` for red blobs, `b` for blue blobs,
+//! `x` for holes and digits for runs of empty cells ; then the side to move (`r` or `b`) ;
+//! then the number of plies played so far. An optional last field gives the board size
+//! (only `8x8` is supported).
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::Board;
+use crate::configuration::Configuration;
+use crate::positions::Positions;
+
+/// Number of cells on a row (and rows on the board).
+const SIDE: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// A position : holes, blobs, side to move and ply counter.
+pub struct Fen {
+    holes: Positions,
+    blobs: [Positions; 2],
+    /// Who should play now (`true` for blue).
+    pub current_player: bool,
+    /// Number of plies played since the start of the game.
+    pub ply: u32,
+}
+
+impl Fen {
+    /// Notation of given `Configuration` after `ply` plies.
+    pub fn from_configuration(configuration: &Configuration, ply: u32) -> Self {
+        Fen {
+            holes: configuration.board().holes,
+            blobs: configuration.blobs(),
+            current_player: configuration.current_player,
+            ply,
+        }
+    }
+
+    /// Notation of the starting position on given `Board`.
+    pub fn from_board(board: &Board) -> Self {
+        Fen::from_configuration(&Configuration::new(board), 0)
+    }
+
+    /// Parse given notation, or if it is not one, load the starting position of the board file
+    /// of this name.
+    pub fn load(string: &str) -> Result<Self, String> {
+        if string.contains('/') {
+            string.parse()
+        } else {
+            Board::load(string)
+                .map(|board| Fen::from_board(&board))
+                .map_err(|e| format!("failed loading board {}: {}", string, e))
+        }
+    }
+
+    /// `Board` of this position.
+    pub fn board(&self) -> Board {
+        Board::new(self.holes)
+    }
+
+    /// `Configuration` of this position on given `Board` (obtained with `board`).
+    pub fn configuration<'a>(&self, board: &'a Board) -> Configuration<'a> {
+        Configuration::from_blobs(board, self.blobs, self.current_player)
+    }
+}
+
+impl fmt::Display for Fen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..SIDE {
+            if row != 0 {
+                write!(f, "/")?;
+            }
+            let mut empty = 0;
+            for column in 0..SIDE {
+                let position = (row * SIDE + column) as u8;
+                let code = if self.holes.contains(position) {
+                    'x'
+                } else if self.blobs[0].contains(position) {
+                    'r'
+                } else if self.blobs[1].contains(position) {
+                    'b'
+                } else {
+                    empty += 1;
+                    continue;
+                };
+                if empty != 0 {
+                    write!(f, "{}", empty)?;
+                    empty = 0;
+                }
+                write!(f, "{}", code)?;
+            }
+            if empty != 0 {
+                write!(f, "{}", empty)?;
+            }
+        }
+        write!(
+            f,
+            " {} {}",
+            if self.current_player { 'b' } else { 'r' },
+            self.ply
+        )
+    }
+}
+
+impl FromStr for Fen {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut fields = string.split_whitespace();
+        let rows = fields.next().ok_or("empty position")?;
+        let mut fen = Fen {
+            holes: Positions::default(),
+            blobs: [Positions::default(); 2],
+            current_player: false,
+            ply: 0,
+        };
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() != SIDE {
+            return Err(format!("expected {} rows, got {}", SIDE, rows.len()));
+        }
+        for (row_index, row) in rows.iter().enumerate() {
+            let mut column = 0;
+            for code in row.chars() {
+                if let Some(run) = code.to_digit(10) {
+                    column += run as usize;
+                    continue;
+                }
+                if column >= SIDE {
+                    return Err(format!("row {} has more than {} cells", row, SIDE));
+                }
+                let cell = Positions::single((row_index * SIDE + column) as u8);
+                match code {
+                    'x' => fen.holes.add(cell),
+                    'r' => fen.blobs[0].add(cell),
+                    'b' => fen.blobs[1].add(cell),
+                    _ => return Err(format!("invalid cell content {}", code)),
+                }
+                column += 1;
+            }
+            if column != SIDE {
+                return Err(format!("row {} does not have {} cells", row, SIDE));
+            }
+        }
+        fen.current_player = match fields.next() {
+            None | Some("r") => false,
+            Some("b") => true,
+            Some(other) => return Err(format!("invalid side to move {}", other)),
+        };
+        if let Some(ply) = fields.next() {
+            fen.ply = ply.parse().map_err(|_| format!("invalid ply {}", ply))?;
+        }
+        match fields.next() {
+            None | Some("8x8") => {}
+            Some(size) => return Err(format!("unsupported board size {}", size)),
+        }
+        if let Some(extra) = fields.next() {
+            return Err(format!("unexpected {}", extra));
+        }
+        Ok(fen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for position in &[
+            "r6b/8/2x2x2/3xx3/3xx3/2x2x2/8/b6r r 0",
+            "8/8/8/8/8/8/8/8 b 12",
+            "rbxrbxrb/xxxxxxxx/7r/b7/1r1b1x2/8/4rr2/xb4xb r 3",
+        ] {
+            let fen: Fen = position.parse().unwrap();
+            assert_eq!(fen.to_string(), *position);
+        }
+        let fen: Fen = "r6b/8/8/8/8/8/8/b6r".parse().unwrap();
+        assert_eq!(fen.to_string(), "r6b/8/8/8/8/8/8/b6r r 0");
+        assert_eq!(fen, "r6b/8/8/8/8/8/8/b6r r 0 8x8".parse().unwrap());
+    }
+
+    #[test]
+    fn configurations_round_trip() {
+        let fen = Fen::load("fortress").unwrap();
+        let board = fen.board();
+        let mut configuration = fen.configuration(&board);
+        for ply in 0..4 {
+            let notation = Fen::from_configuration(&configuration, ply);
+            let parsed: Fen = notation.to_string().parse().unwrap();
+            assert_eq!(parsed, notation);
+            assert_eq!(parsed.board().holes, board.holes);
+            assert_eq!(
+                parsed.configuration(&board).serialize(),
+                configuration.serialize()
+            );
+            let movement = configuration.movements().next().unwrap();
+            configuration.apply_movement(&movement);
+        }
+    }
+
+    #[test]
+    fn invalid_positions_are_refused() {
+        for position in &[
+            "",
+            "8/8/8/8/8/8/8",
+            "8/8/8/8/8/8/8/8/8",
+            "8r/8/8/8/8/8/8/8",
+            "r7rr/8/8/8/8/8/8/8",
+            "9/8/8/8/8/8/8/8",
+            "7/8/8/8/8/8/8/8",
+            "r6q/8/8/8/8/8/8/8",
+            "8/8/8/8/8/8/8/8 g",
+            "8/8/8/8/8/8/8/8 r -1",
+            "8/8/8/8/8/8/8/8 r 0 10x10",
+            "8/8/8/8/8/8/8/8 r 0 8x8 more",
+        ] {
+            assert!(position.parse::<Fen>().is_err(), "{} accepted", position);
+        }
+    }
+}
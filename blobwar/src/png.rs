@@ -0,0 +1,558 @@
+//! PNG diagrams of configurations, drawn like the SVG ones for tools which only take bitmaps.
+//!
+//! Pictures use a small palette and a simple run-length compression. A whole game can also be
+//! drawn as one animated PNG, showing each ply in turn.
+use crate::configuration::Configuration;
+use crate::positions::Position;
+use crate::render::Markers;
+use crate::svg::{center, CELL, MARGIN, SIZE};
+
+/// Colors of the palette, in order.
+const PALETTE: [[u8; 3]; 7] = [
+    [0xff, 0xff, 0xff],
+    [0x00, 0x00, 0x00],
+    [0x40, 0x40, 0x40],
+    [0xe8, 0xe8, 0xe8],
+    [0xff, 0xe6, 0x80],
+    [0xd6, 0x27, 0x28],
+    [0x1f, 0x77, 0xb4],
+];
+const WHITE: u8 = 0;
+const BLACK: u8 = 1;
+const HOLE: u8 = 2;
+const EMPTY: u8 = 3;
+const FLIPPED: u8 = 4;
+const RED: u8 = 5;
+const BLUE: u8 = 6;
+
+/// Coordinates glyphs, 3 pixels wide and 5 high (one byte per row, leftmost pixel first).
+const GLYPHS: [(char, [u8; 5]); 16] = [
+    ('a', [0b000, 0b011, 0b101, 0b101, 0b011]),
+    ('b', [0b100, 0b110, 0b101, 0b101, 0b110]),
+    ('c', [0b000, 0b011, 0b100, 0b100, 0b011]),
+    ('d', [0b001, 0b011, 0b101, 0b101, 0b011]),
+    ('e', [0b000, 0b010, 0b111, 0b100, 0b011]),
+    ('f', [0b011, 0b100, 0b110, 0b100, 0b100]),
+    ('g', [0b011, 0b101, 0b011, 0b001, 0b110]),
+    ('h', [0b100, 0b110, 0b101, 0b101, 0b101]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b110, 0b101, 0b010]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b010, 0b101, 0b010, 0b101, 0b010]),
+];
+
+/// Size of a glyph pixel.
+const SCALE: i32 = 3;
+
+/// Palette index of every pixel, row by row.
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Canvas {
+            pixels: vec![WHITE; (SIZE * SIZE) as usize],
+        }
+    }
+
+    /// Paint all pixels (of given bounding box) inside given shape.
+    fn fill<F: Fn(i32, i32) -> bool>(
+        &mut self,
+        bounds: (i32, i32, i32, i32),
+        color: u8,
+        inside: F,
+    ) {
+        let (left, top, right, bottom) = bounds;
+        let size = SIZE as i32;
+        for y in top.max(0)..bottom.min(size) {
+            for x in left.max(0)..right.min(size) {
+                if inside(x, y) {
+                    self.pixels[(y * size + x) as usize] = color;
+                }
+            }
+        }
+    }
+
+    fn rectangle(&mut self, left: i32, top: i32, size: i32, color: u8) {
+        self.fill((left, top, left + size, top + size), color, |_, _| true);
+    }
+
+    /// Pixels between `inner` and `outer` radius around given center.
+    fn ring(&mut self, (x, y): (i32, i32), inner: i32, outer: i32, color: u8) {
+        let bounds = (x - outer, y - outer, x + outer + 1, y + outer + 1);
+        self.fill(bounds, color, |px, py| {
+            let distance = (px - x).pow(2) + (py - y).pow(2);
+            distance >= inner * inner && distance <= outer * outer
+        });
+    }
+
+    /// Arrow of given width from `from` to the tip `to`.
+    fn arrow(&mut self, from: (i32, i32), to: (i32, i32), width: i32, color: u8) {
+        let (dx, dy) = ((to.0 - from.0) as f64, (to.1 - from.1) as f64);
+        let length = (dx * dx + dy * dy).sqrt();
+        let head = 4.0 * width as f64;
+        let bounds = (
+            from.0.min(to.0) - head as i32,
+            from.1.min(to.1) - head as i32,
+            from.0.max(to.0) + head as i32 + 1,
+            from.1.max(to.1) + head as i32 + 1,
+        );
+        self.fill(bounds, color, |x, y| {
+            // coordinates along the arrow and across it
+            let (x, y) = ((x - from.0) as f64, (y - from.1) as f64);
+            let along = (x * dx + y * dy) / length;
+            let across = (y * dx - x * dy).abs() / length;
+            if along < 0.0 || along > length {
+                false
+            } else if along > length - head {
+                across <= (length - along) / 2.0
+            } else {
+                across <= width as f64 / 2.0
+            }
+        });
+    }
+
+    /// Glyph of given character centered on given point.
+    fn glyph(&mut self, character: char, (x, y): (i32, i32)) {
+        let rows = match GLYPHS.iter().find(|(c, _)| *c == character) {
+            Some((_, rows)) => rows,
+            None => return,
+        };
+        let (left, top) = (x - 3 * SCALE / 2, y - 5 * SCALE / 2);
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    let (px, py) = (left + column * SCALE, top + row as i32 * SCALE);
+                    self.fill((px, py, px + SCALE, py + SCALE), BLACK, |_, _| true);
+                }
+            }
+        }
+    }
+}
+
+fn pixel_center(position: Position) -> (i32, i32) {
+    let (x, y) = center(position);
+    (x as i32, y as i32)
+}
+
+/// Coordinates, cells, blobs and markers of one configuration.
+fn draw(configuration: &Configuration, markers: &Markers) -> Canvas {
+    let mut canvas = Canvas::new();
+    let (cell, margin) = (CELL as i32, MARGIN as i32);
+    for index in 0..8 {
+        let offset = margin + index * cell + cell / 2;
+        canvas.glyph((b'a' + index as u8) as char, (offset, margin / 2));
+        canvas.glyph((b'1' + index as u8) as char, (margin / 2, offset));
+    }
+    let holes = configuration.board().holes;
+    let blobs = configuration.blobs();
+    for position in 0..64 {
+        let (x, y) = pixel_center(position);
+        let fill = if holes.contains(position) {
+            HOLE
+        } else if markers.flipped.contains(position) {
+            FLIPPED
+        } else {
+            EMPTY
+        };
+        // one pixel of white around cells
+        canvas.rectangle(x - cell / 2 + 1, y - cell / 2 + 1, cell - 2, fill);
+        if blobs[0].contains(position) {
+            canvas.ring((x, y), 0, cell * 2 / 5, RED);
+        } else if blobs[1].contains(position) {
+            canvas.ring((x, y), 0, cell * 2 / 5, BLUE);
+        }
+    }
+    if let Some(destination) = markers.destination {
+        let to = pixel_center(destination);
+        match markers.source {
+            Some(source) => canvas.arrow(pixel_center(source), to, 3, BLACK),
+            None => canvas.ring(to, cell * 2 / 5 - 3, cell * 2 / 5, BLACK),
+        }
+    }
+    canvas
+}
+
+/// CRC of PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Deflate length codes : base length and number of extra bits, from code 257.
+const LENGTHS: [(usize, u32); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// Longest deflate match.
+const MAX_MATCH: usize = 258;
+
+/// Bits written least significant first, as deflate wants them.
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    used: u32,
+}
+
+impl Bits {
+    fn write(&mut self, value: u32, count: u32) {
+        for bit in 0..count {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= (((value >> bit) & 1) as u8) << self.used;
+            self.used = (self.used + 1) % 8;
+        }
+    }
+
+    /// Huffman codes are written most significant bit first.
+    fn code(&mut self, code: u32, count: u32) {
+        let reversed = (0..count).fold(0, |r, bit| (r << 1) | ((code >> bit) & 1));
+        self.write(reversed, count);
+    }
+
+    /// Literal or length symbol, with the fixed Huffman codes.
+    fn symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    /// Copy of `length` bytes from `distance` bytes back, distance being 1 or a scanline.
+    fn repeat(&mut self, length: usize, distance: usize) {
+        let code = LENGTHS
+            .iter()
+            .rposition(|&(base, _)| base <= length)
+            .unwrap();
+        let (base, extra) = LENGTHS[code];
+        self.symbol(257 + code as u32);
+        self.write((length - base) as u32, extra);
+        // distance codes are five bits long, scanlines need code 16 (257 to 384)
+        match distance {
+            1 => self.code(0, 5),
+            257..=384 => {
+                self.code(16, 5);
+                self.write(distance as u32 - 257, 7);
+            }
+            _ => unreachable!("no code for distance {}", distance),
+        }
+    }
+}
+
+/// Zlib stream of given scanlines, in one deflate block of fixed codes. Pictures being mostly
+/// flat, only repeats of the previous byte or of the previous scanline are looked for.
+fn zlib(data: &[u8], scanline: usize) -> Vec<u8> {
+    let mut bits = Bits::default();
+    // last block, fixed codes
+    bits.write(1, 1);
+    bits.write(1, 2);
+    let mut index = 0;
+    while index < data.len() {
+        let matching = |distance: usize| {
+            if index < distance {
+                return 0;
+            }
+            data[index..]
+                .iter()
+                .zip(&data[index - distance..])
+                .take(MAX_MATCH)
+                .take_while(|(a, b)| a == b)
+                .count()
+        };
+        let (length, distance) = [(matching(1), 1), (matching(scanline), scanline)]
+            .iter()
+            .cloned()
+            .max()
+            .unwrap();
+        if length >= 3 {
+            bits.repeat(length, distance);
+            index += length;
+        } else {
+            bits.symbol(data[index] as u32);
+            index += 1;
+        }
+    }
+    bits.symbol(256);
+
+    let mut stream = vec![0x78, 0x01];
+    stream.extend_from_slice(&bits.bytes);
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    stream
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Compressed scanlines of given canvas.
+fn image_data(canvas: &Canvas) -> Vec<u8> {
+    let mut scanlines = Vec::with_capacity(canvas.pixels.len() + SIZE as usize);
+    for row in canvas.pixels.chunks(SIZE as usize) {
+        // no filter
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+    zlib(&scanlines, SIZE as usize + 1)
+}
+
+/// Signature, header and palette, `frames` frames if animated.
+fn start(frames: Option<usize>) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend_from_slice(&SIZE.to_be_bytes());
+    header.extend_from_slice(&SIZE.to_be_bytes());
+    // 8 bits per pixel, palette, default compression, filters and no interlace
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"PLTE", &PALETTE.concat());
+    if let Some(frames) = frames {
+        let mut control = (frames as u32).to_be_bytes().to_vec();
+        // loop forever
+        control.extend_from_slice(&0u32.to_be_bytes());
+        chunk(&mut png, b"acTL", &control);
+    }
+    png
+}
+
+/// PNG picture of `configuration`, highlighting `markers`.
+pub fn diagram(configuration: &Configuration, markers: &Markers) -> Vec<u8> {
+    let mut png = start(None);
+    chunk(
+        &mut png,
+        b"IDAT",
+        &image_data(&draw(configuration, markers)),
+    );
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Animated PNG showing each configuration during `delay` milliseconds (at most a minute),
+/// looping forever. `markers` gives the markers of each configuration.
+pub fn animation(configurations: &[Configuration], markers: &[Markers], delay: u64) -> Vec<u8> {
+    let mut png = start(Some(configurations.len()));
+    // frame controls and frame data share one sequence
+    let mut sequence = 0u32;
+    for (index, (configuration, markers)) in configurations.iter().zip(markers).enumerate() {
+        let mut control = sequence.to_be_bytes().to_vec();
+        for value in &[SIZE, SIZE, 0, 0] {
+            control.extend_from_slice(&value.to_be_bytes());
+        }
+        // delay in milliseconds, no disposal and no blending
+        control.extend_from_slice(&(delay.min(60_000) as u16).to_be_bytes());
+        control.extend_from_slice(&1000u16.to_be_bytes());
+        control.extend_from_slice(&[0, 0]);
+        chunk(&mut png, b"fcTL", &control);
+        sequence += 1;
+        let data = image_data(&draw(configuration, markers));
+        if index == 0 {
+            chunk(&mut png, b"IDAT", &data);
+        } else {
+            let mut frame = sequence.to_be_bytes().to_vec();
+            frame.extend_from_slice(&data);
+            chunk(&mut png, b"fdAT", &frame);
+            sequence += 1;
+        }
+    }
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::Fen;
+    use crate::positions::BoardPosition;
+
+    /// All chunks (kind and data) of a PNG file, checking their CRC.
+    fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + length]);
+            let crc = &rest[8 + length..12 + length];
+            assert_eq!(crc, crc32(&rest[4..8 + length]).to_be_bytes());
+            chunks.push((String::from_utf8(kind.to_vec()).unwrap(), data.to_vec()));
+            rest = &rest[12 + length..];
+        }
+        chunks
+    }
+
+    /// Bits of a deflate stream, least significant first.
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        position: usize,
+    }
+
+    impl Reader<'_> {
+        fn bits(&mut self, count: u32) -> usize {
+            (0..count).fold(0, |value, bit| {
+                let byte = self.bytes[self.position / 8];
+                self.position += 1;
+                value | ((byte >> ((self.position - 1) % 8)) as usize & 1) << bit
+            })
+        }
+
+        fn code(&mut self, count: u32) -> usize {
+            (0..count).fold(0, |code, _| (code << 1) | self.bits(1))
+        }
+
+        fn symbol(&mut self) -> usize {
+            let code = self.code(7);
+            if code <= 0x17 {
+                return 256 + code;
+            }
+            let code = (code << 1) | self.bits(1);
+            match code {
+                0x30..=0xbf => code - 0x30,
+                0xc0..=0xc7 => 280 + code - 0xc0,
+                _ => 144 + ((code << 1) | self.bits(1)) - 0x190,
+            }
+        }
+    }
+
+    /// Pixels of given image data, inflating the one fixed codes block `zlib` writes.
+    fn pixels(data: &[u8]) -> Vec<u8> {
+        let mut reader = Reader {
+            bytes: &data[2..data.len() - 4],
+            position: 0,
+        };
+        assert_eq!((reader.bits(1), reader.bits(2)), (1, 1));
+        let mut scanlines: Vec<u8> = Vec::new();
+        loop {
+            let symbol = reader.symbol();
+            match symbol {
+                0..=255 => scanlines.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let (base, extra) = LENGTHS[symbol - 257];
+                    let length = base + reader.bits(extra);
+                    let distance = match reader.code(5) {
+                        0 => 1,
+                        16 => 257 + reader.bits(7),
+                        code => panic!("unexpected distance code {}", code),
+                    };
+                    for _ in 0..length {
+                        scanlines.push(scanlines[scanlines.len() - distance]);
+                    }
+                }
+            }
+        }
+        scanlines
+            .chunks(SIZE as usize + 1)
+            .flat_map(|row| row[1..].to_vec())
+            .collect()
+    }
+
+    fn pixel(pixels: &[u8], (x, y): (i32, i32)) -> u8 {
+        pixels[(y * SIZE as i32 + x) as usize]
+    }
+
+    #[test]
+    fn diagrams() {
+        let fen: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+        let board = fen.board();
+        let before = fen.configuration(&board);
+        let movement = "a1-c3".parse().ok();
+        let after = before.play(&movement.unwrap());
+        let png = diagram(&after, &Markers::new(&before, &movement));
+        let chunks = chunks(&png);
+        let kinds: Vec<&str> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, vec!["IHDR", "PLTE", "IDAT", "IEND"]);
+        assert_eq!(&chunks[0].1[..8], &[0, 0, 1, 112, 0, 0, 1, 112]);
+
+        let pixels = pixels(&chunks[2].1);
+        assert_eq!(pixels.len(), (SIZE * SIZE) as usize);
+        let at = |x, y| pixel_center(Position::from_2d(x, y));
+        assert_eq!(pixel(&pixels, at(7, 0)), BLUE);
+        assert_eq!(pixel(&pixels, at(7, 7)), RED);
+        assert_eq!(pixel(&pixels, at(3, 3)), EMPTY);
+        // the arrow goes through b2, from a1 to c3
+        assert_eq!(pixel(&pixels, at(1, 1)), BLACK);
+        assert_eq!(pixel(&pixels, at(0, 0)), BLACK);
+        assert_eq!(pixel(&pixels, (0, 0)), WHITE);
+    }
+
+    #[test]
+    fn animations() {
+        let fen: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+        let board = fen.board();
+        let first = fen.configuration(&board);
+        let movement = "b2".parse().ok();
+        let second = first.play(&movement.unwrap());
+        let markers = [Markers::default(), Markers::new(&first, &movement)];
+        let png = animation(&[first, second], &markers, 500);
+        let chunks = chunks(&png);
+        let kinds: Vec<&str> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            vec!["IHDR", "PLTE", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "IEND"]
+        );
+        assert_eq!(&chunks[2].1, &[0, 0, 0, 2, 0, 0, 0, 0]);
+        // sequence numbers follow each other
+        assert_eq!(&chunks[5].1[..4], &[0, 0, 0, 1]);
+        assert_eq!(&chunks[6].1[..4], &[0, 0, 0, 2]);
+        assert_eq!(&chunks[5].1[20..24], &[1, 244, 3, 232]);
+        // the second frame shows the duplicated blob
+        let pixels = pixels(&chunks[6].1[4..]);
+        assert_eq!(pixel(&pixels, pixel_center(Position::from_2d(1, 1))), RED);
+    }
+}
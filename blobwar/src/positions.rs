@@ -31,7 +31,7 @@ impl BoardPosition for Position {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 /// Set of `Position` as a bitfield (position 0 is bit of lowest weight)
 /// This allows to store any combination of board cells in a very compact manner.
 /// Moreover we can then use bit masking operations to compute intersections and unions...
@@ -73,7 +73,7 @@ impl Positions {
     }
     /// Do we contain nothing ?
     pub fn is_empty(&self) -> bool {
-        self.eq(&0)
+        self.0 == 0
     }
     /// How many bits are set ?
     pub fn len(&self) -> i8 {
@@ -0,0 +1,290 @@
+//! Network protocol between a server and remote players.
+//!
+//! Messages are JSON objects, one per line, tagged by their `type` :
+//! ```text
+//! {"type":"hello","name":"alphabeta:depth=4","version":1}
+//! {"type":"new_game","board":"r6b/8/8/8/8/8/8/b6r r 0","color":"red"}
+//! {"type":"position","position":"r6b/8/8/8/8/8/8/b6r r 0"}
+//! {"type":"move","move":"b2"}
+//! {"type":"game_over","result":3,"termination":null}
+//! {"type":"error","message":"illegal move"}
+//! ```
+//! Both sides start by sending `hello` ; then for each game the server sends `new_game`, a
+//! `position` each time the client must play (answered by a `move`, possibly `"pass"`) and
+//! `game_over` with the final red value and position.
+//!
+//! Servers accepting reconnections send a `session` token after the handshake. A client losing
+//! its connection can then connect again within the grace period, say `hello` and `resume` with
+//! the token ; the server acknowledges with the same `session` and sends the current `position`
+//! again, with the remaining clock.
+//!
+//! Lobby servers also accept `list_games`, `create_game`, `join_game`, `pair` and
+//! `get_leaderboard` between games (see `lobby`).
+//!
+//! Spectators (see `broadcast`) also say `hello`, then only receive : a `header` when a game
+//! starts, then `played` for each move, `take_back` on undos and `game_over`.
+//!
+//! The legacy protocol (`Configuration::serialize` strings one way, JSON `Option<Movement>` the
+//! other, no handshake) is still spoken when asked for.
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::net::TcpStream;
+
+use serde::{Deserializer, Serializer};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::configuration::{format_move, parse_move, Movement};
+
+/// Version of the protocol spoken by this crate.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Side played in a game.
+pub enum Color {
+    /// Red plays first on the standard board.
+    Red,
+    /// Blue.
+    Blue,
+}
+
+impl Color {
+    /// Color of given player (`true` for blue, like `Configuration::current_player`).
+    pub fn from_player(player: bool) -> Self {
+        if player {
+            Color::Blue
+        } else {
+            Color::Red
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// All messages exchanged.
+pub enum Message {
+    /// First message on each side.
+    Hello {
+        /// Name of the engine (or server).
+        name: String,
+        /// `PROTOCOL_VERSION` of the sender.
+        version: u32,
+    },
+    /// A new game starts.
+    NewGame {
+        /// Starting position of the board (see `notation`).
+        board: String,
+        /// Color played by the client.
+        color: Color,
+    },
+    /// Client's turn to play in this position (see `notation`).
+    Position {
+        /// Current position.
+        position: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        /// Remaining thinking time in milliseconds, if the client has a clock.
+        clock: Option<u64>,
+    },
+    /// Client's move.
+    Move {
+        #[serde(
+            rename = "move",
+            serialize_with = "write_move",
+            deserialize_with = "read_move"
+        )]
+        /// Move played (`None` to pass).
+        movement: Option<Movement>,
+    },
+    /// The game is over.
+    GameOver {
+        /// Number of red blobs minus number of blue blobs.
+        result: i8,
+        /// How the game ended if not on the board.
+        termination: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        /// Final position (see `notation`).
+        position: Option<String>,
+    },
+    /// Something went wrong.
+    Error {
+        /// What went wrong.
+        message: String,
+    },
+    /// The client may reconnect with this token if it loses its connection.
+    Session {
+        /// Token to give to `resume`.
+        token: String,
+        /// Time in milliseconds the server waits for a reconnection.
+        grace: u64,
+    },
+    /// Take over the session of a lost connection, right after `hello`.
+    Resume {
+        /// Token received in `session`.
+        token: String,
+    },
+    /// Ask a lobby for its open games.
+    ListGames,
+    /// Open games of a lobby.
+    Games {
+        /// All games waiting for an opponent.
+        games: Vec<OpenGame>,
+    },
+    /// Open a game in a lobby and wait for an opponent.
+    CreateGame {
+        /// Board (file or position), the lobby's default one if not given.
+        board: Option<String>,
+    },
+    /// Game opened, waiting for an opponent.
+    GameCreated {
+        /// Id to give to `join_game`.
+        id: usize,
+    },
+    /// Join an open game.
+    JoinGame {
+        /// Id of the game.
+        id: usize,
+    },
+    /// Play against the next client asking for it.
+    Pair,
+    /// Ask a lobby for its leaderboard.
+    GetLeaderboard,
+    /// Results of all players of a lobby, best first.
+    Leaderboard {
+        /// One entry per player name.
+        entries: Vec<LeaderboardEntry>,
+    },
+    /// A game starts (sent to spectators).
+    Header {
+        /// Red player.
+        red: String,
+        /// Blue player.
+        blue: String,
+        /// Starting position (see `notation`).
+        board: String,
+        /// Date of the game (YYYY-MM-DD).
+        date: String,
+    },
+    /// A move was played (sent to spectators).
+    Played {
+        /// Number of moves played before this one.
+        ply: usize,
+        #[serde(
+            rename = "move",
+            serialize_with = "write_move",
+            deserialize_with = "read_move"
+        )]
+        /// Move played (`None` for a pass).
+        movement: Option<Movement>,
+        /// Thinking time in milliseconds.
+        time: Option<u64>,
+        /// When it was played, in milliseconds since the Unix epoch.
+        timestamp: u64,
+    },
+    /// Moves were taken back (sent to spectators).
+    TakeBack {
+        /// Number of moves left.
+        ply: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// A game waiting for an opponent in a lobby.
+pub struct OpenGame {
+    /// Id to give to `join_game`.
+    pub id: usize,
+    /// Name of the waiting player.
+    pub host: String,
+    /// Starting position (see `notation`).
+    pub board: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Results of one player in a lobby.
+pub struct LeaderboardEntry {
+    /// Name given in `hello`.
+    pub name: String,
+    /// Games won.
+    pub wins: usize,
+    /// Games drawn.
+    pub draws: usize,
+    /// Games lost.
+    pub losses: usize,
+}
+
+fn write_move<S: Serializer>(
+    movement: &Option<Movement>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_move(movement))
+}
+
+fn read_move<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Movement>, D::Error> {
+    let text: String = serde::Deserialize::deserialize(deserializer)?;
+    parse_move(&text).map_err(serde::de::Error::custom)
+}
+
+/// Messages over a TCP stream, one JSON object per line.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Speak the protocol on given stream.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// Underlying stream.
+    pub fn stream(&self) -> &TcpStream {
+        &self.writer
+    }
+
+    /// Send given message.
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mut line = serde_json::to_string(message).expect("failed serializing message");
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())
+    }
+
+    /// Wait for next message. Fails on closed connections and invalid messages.
+    pub fn receive(&mut self) -> io::Result<Message> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed",
+            ));
+        }
+        serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Send our `hello` and check the other side's one, returning its name.
+    pub fn handshake(&mut self, name: &str) -> io::Result<String> {
+        self.send(&Message::Hello {
+            name: name.to_owned(),
+            version: PROTOCOL_VERSION,
+        })?;
+        match self.receive()? {
+            Message::Hello { name, version } if version == PROTOCOL_VERSION => Ok(name),
+            Message::Hello { version, .. } => {
+                let message = format!(
+                    "unsupported protocol version {} (we speak {})",
+                    version, PROTOCOL_VERSION
+                );
+                let _ = self.send(&Message::Error {
+                    message: message.clone(),
+                });
+                Err(io::Error::new(io::ErrorKind::InvalidData, message))
+            }
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected hello, got {:?}", other),
+            )),
+        }
+    }
+}
@@ -0,0 +1,519 @@
+//! Game records: save, load and replay whole games.
+//!
+//! A record is a text file with a header of `[Key "value"]` tags followed by one line per move:
+//!
+//! ```text
+//! [Position "r6b/8/2x2x2/8/8/2x2x2/8/b6r r 0"]
+//! [Red "alphabeta:depth=4"]
+//! [Blue "greedy"]
+//! [Result "4"]
+//! [Termination "blue resigned"]
+//! [Date "2023-05-12"]
+//!
+//! 1. a1-c3 score=3 time=120 {first jump}
+//! 2. g2
+//! 3. pass
+//! ```
+//!
+//! The starting position uses the notation of `notation`.
+//! The result is the final red value (#red - #blue).
+//! The termination tag is only present if the game did not go to its end : `red resigned`,
+//! `blue forfeited (timeout)` (or `illegal move`, `disconnection`).
+//! Moves use the algebraic notation of `Movement` (`b2`, `a1-c3`) or `pass`.
+//! `score` and `time` (in milliseconds) and the `{comment}` are optional.
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::Board;
+use crate::configuration::{format_move, parse_move, Configuration, Movement};
+use crate::notation::Fen;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// One move with its annotations.
+pub struct RecordedMove {
+    /// What was played (`None` for a pass).
+    pub movement: Option<Movement>,
+    /// Evaluation given by the engine.
+    pub score: Option<i8>,
+    /// Thinking time in milliseconds.
+    pub time: Option<u64>,
+    /// Free text.
+    pub comment: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Why a game stopped before its end.
+pub enum Termination {
+    /// Given player (`true` for blue) resigned.
+    Resignation(bool),
+    /// Given player (`true` for blue) lost by forfeit.
+    Forfeit(bool, ForfeitReason),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Why a (remote) player forfeited.
+pub enum ForfeitReason {
+    /// It played an illegal move.
+    IllegalMove,
+    /// It did not answer in time.
+    Timeout,
+    /// It disconnected (or sent garbage).
+    Disconnection,
+}
+
+impl Termination {
+    /// Player losing the game.
+    pub fn loser(&self) -> bool {
+        match *self {
+            Termination::Resignation(player) | Termination::Forfeit(player, _) => player,
+        }
+    }
+}
+
+impl fmt::Display for ForfeitReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ForfeitReason::IllegalMove => "illegal move",
+                ForfeitReason::Timeout => "timeout",
+                ForfeitReason::Disconnection => "disconnection",
+            }
+        )
+    }
+}
+
+impl FromStr for ForfeitReason {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "illegal move" => Ok(ForfeitReason::IllegalMove),
+            "timeout" => Ok(ForfeitReason::Timeout),
+            "disconnection" => Ok(ForfeitReason::Disconnection),
+            other => Err(format!("invalid forfeit reason {}", other)),
+        }
+    }
+}
+
+fn side_name(player: bool) -> &'static str {
+    if player {
+        "blue"
+    } else {
+        "red"
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Termination::Resignation(player) => write!(f, "{} resigned", side_name(player)),
+            Termination::Forfeit(player, reason) => {
+                write!(f, "{} forfeited ({})", side_name(player), reason)
+            }
+        }
+    }
+}
+
+impl FromStr for Termination {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "red resigned" => Ok(Termination::Resignation(false)),
+            "blue resigned" => Ok(Termination::Resignation(true)),
+            other => {
+                let (side, reason) = other
+                    .strip_suffix(')')
+                    .and_then(|o| o.split_once(" forfeited ("))
+                    .ok_or_else(|| format!("invalid termination {}", other))?;
+                let player = match side {
+                    "red" => false,
+                    "blue" => true,
+                    _ => return Err(format!("invalid side {}", side)),
+                };
+                Ok(Termination::Forfeit(player, reason.parse()?))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A whole game.
+pub struct GameRecord {
+    /// Starting position.
+    pub start: Fen,
+    /// Red player.
+    pub red: String,
+    /// Blue player.
+    pub blue: String,
+    /// Final red value if the game is over.
+    pub result: Option<i8>,
+    /// Set if the game stopped early.
+    pub termination: Option<Termination>,
+    /// Date of the game (YYYY-MM-DD).
+    pub date: String,
+    /// All moves in order.
+    pub moves: Vec<RecordedMove>,
+}
+
+impl GameRecord {
+    /// Start recording a new game from given `Configuration`, dated today.
+    pub fn new(start: &Configuration, red: &str, blue: &str) -> Self {
+        GameRecord {
+            start: Fen::from_configuration(start, 0),
+            red: red.to_owned(),
+            blue: blue.to_owned(),
+            result: None,
+            termination: None,
+            date: today(),
+            moves: Vec::new(),
+        }
+    }
+
+    /// Winner of a finished game (`true` for blue), `None` for a draw or an unfinished game.
+    pub fn winner(&self) -> Option<bool> {
+        if let Some(termination) = self.termination {
+            return Some(!termination.loser());
+        }
+        match self.result? {
+            x if x > 0 => Some(false),
+            x if x < 0 => Some(true),
+            _ => None,
+        }
+    }
+
+    /// `Board` of this game.
+    pub fn board(&self) -> Board {
+        self.start.board()
+    }
+
+    /// Replay the game on given `Board` (obtained with `board`).
+    /// Return all successive configurations (starting one included) and check every move and
+    /// the result.
+    pub fn replay<'a>(&self, board: &'a Board) -> Result<Vec<Configuration<'a>>, String> {
+        let mut configuration = self.start.configuration(board);
+        let mut configurations = vec![configuration];
+        for (ply, recorded) in self.moves.iter().enumerate() {
+            if let Some(ref movement) = recorded.movement {
+                if configuration.game_over() || !configuration.check_move(movement) {
+                    return Err(format!(
+                        "illegal move {} at ply {}",
+                        format_move(&recorded.movement),
+                        ply + 1
+                    ));
+                }
+                configuration.apply_movement(movement);
+            } else {
+                // passing is only allowed when stuck
+                if configuration.movements().next().is_some() {
+                    return Err(format!("illegal pass at ply {}", ply + 1));
+                }
+                configuration = configuration.skip_play();
+            }
+            configurations.push(configuration);
+        }
+        if let Some(result) = self.result {
+            if result != configuration.red_value() {
+                return Err(format!(
+                    "result {} does not match final red value {}",
+                    result,
+                    configuration.red_value()
+                ));
+            }
+        }
+        Ok(configurations)
+    }
+
+    /// Save into given file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Load and validate given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let record: GameRecord = fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        record
+            .replay(&record.board())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(record)
+    }
+}
+
+/// Today's date as YYYY-MM-DD (UTC).
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    date(days)
+}
+
+/// Date (YYYY-MM-DD) of given number of days since 1970-01-01.
+fn date(days: i64) -> String {
+    // civil from days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[Position \"{}\"]", self.start)?;
+        writeln!(f, "[Red \"{}\"]", self.red)?;
+        writeln!(f, "[Blue \"{}\"]", self.blue)?;
+        if let Some(result) = self.result {
+            writeln!(f, "[Result \"{}\"]", result)?;
+        }
+        if let Some(termination) = self.termination {
+            writeln!(f, "[Termination \"{}\"]", termination)?;
+        }
+        writeln!(f, "[Date \"{}\"]", self.date)?;
+        writeln!(f)?;
+        for (ply, recorded) in self.moves.iter().enumerate() {
+            writeln!(f, "{}. {}", ply + 1, recorded)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RecordedMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_move(&self.movement))?;
+        if let Some(score) = self.score {
+            write!(f, " score={}", score)?;
+        }
+        if let Some(time) = self.time {
+            write!(f, " time={}", time)?;
+        }
+        if let Some(ref comment) = self.comment {
+            write!(f, " {{{}}}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RecordedMove {
+    type Err = String;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (line, comment) = match line.split_once('{') {
+            Some((before, comment)) => (
+                before,
+                Some(
+                    comment
+                        .strip_suffix('}')
+                        .ok_or("unterminated comment")?
+                        .to_owned(),
+                ),
+            ),
+            None => (line, None),
+        };
+        let mut words = line.split_whitespace();
+        let movement = parse_move(words.next().ok_or("missing move")?)?;
+        let mut recorded = RecordedMove {
+            movement,
+            comment,
+            ..Default::default()
+        };
+        for word in words {
+            match word.split_once('=') {
+                Some(("score", score)) => {
+                    recorded.score = Some(
+                        score
+                            .parse()
+                            .map_err(|_| format!("invalid score {}", score))?,
+                    )
+                }
+                Some(("time", time)) => {
+                    recorded.time =
+                        Some(time.parse().map_err(|_| format!("invalid time {}", time))?)
+                }
+                _ => return Err(format!("unexpected {}", word)),
+            }
+        }
+        Ok(recorded)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut start = None;
+        let mut record = GameRecord {
+            // replaced by the mandatory Position tag
+            start: Fen::from_board(&Board::default()),
+            red: String::new(),
+            blue: String::new(),
+            result: None,
+            termination: None,
+            date: String::new(),
+            moves: Vec::new(),
+        };
+        for line in string.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let (key, value) = tag
+                    .strip_suffix("\"]")
+                    .and_then(|t| t.split_once(" \""))
+                    .ok_or_else(|| format!("invalid tag {}", line))?;
+                match key {
+                    "Position" => start = Some(value.parse()?),
+                    "Red" => record.red = value.to_owned(),
+                    "Blue" => record.blue = value.to_owned(),
+                    "Result" => {
+                        record.result = Some(
+                            value
+                                .parse()
+                                .map_err(|_| format!("invalid result {}", value))?,
+                        )
+                    }
+                    "Termination" => record.termination = Some(value.parse()?),
+                    "Date" => record.date = value.to_owned(),
+                    _ => {} // unknown tags are ignored
+                }
+            } else {
+                let (number, recorded) = line
+                    .split_once(". ")
+                    .ok_or_else(|| format!("missing move number in {}", line))?;
+                if number.parse() != Ok(record.moves.len() + 1) {
+                    return Err(format!("unexpected move number {}", number));
+                }
+                record.moves.push(recorded.parse()?);
+            }
+        }
+        record.start = start.ok_or("missing Position tag")?;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few plies from a board with holes, the first one annotated.
+    fn game() -> GameRecord {
+        let fen: Fen = "r6b/8/2x2x2/8/8/2x2x2/8/b6r".parse().unwrap();
+        let board = fen.board();
+        let mut configuration = fen.configuration(&board);
+        let mut record = GameRecord::new(&configuration, "alphabeta:depth=4", "greedy");
+        for _ in 0..3 {
+            let movement = configuration.movements().next().unwrap();
+            configuration.apply_movement(&movement);
+            record.moves.push(RecordedMove {
+                movement: Some(movement),
+                ..Default::default()
+            });
+        }
+        record.moves[0].score = Some(-3);
+        record.moves[0].time = Some(120);
+        record.moves[0].comment = Some("first move".to_owned());
+        record.result = Some(configuration.red_value());
+        record.termination = Some(Termination::Forfeit(true, ForfeitReason::Timeout));
+        record
+    }
+
+    #[test]
+    fn round_trip() {
+        let record = game();
+        let text = record.to_string();
+        assert!(text.contains("[Termination \"blue forfeited (timeout)\"]"));
+        assert!(text.contains("1. "));
+        assert!(text.contains(" score=-3 time=120 {first move}"));
+        let parsed: GameRecord = text.parse().unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.winner(), Some(false));
+        let board = parsed.board();
+        let configurations = parsed.replay(&board).unwrap();
+        assert_eq!(configurations.len(), 4);
+        assert_eq!(Fen::from_configuration(&configurations[0], 0), record.start);
+    }
+
+    #[test]
+    fn passes_and_terminations() {
+        for termination in &[
+            Termination::Resignation(false),
+            Termination::Resignation(true),
+            Termination::Forfeit(false, ForfeitReason::IllegalMove),
+            Termination::Forfeit(true, ForfeitReason::Disconnection),
+        ] {
+            assert_eq!(termination.to_string().parse(), Ok(*termination));
+        }
+        let pass: RecordedMove = "pass {nothing to do}".parse().unwrap();
+        assert_eq!(pass.movement, None);
+        assert_eq!(pass.comment.as_deref(), Some("nothing to do"));
+    }
+
+    #[test]
+    fn malformed_records_are_refused() {
+        let text = game().to_string();
+        let broken = |from: &str, to: &str| {
+            assert!(text.contains(from));
+            text.replacen(from, to, 1)
+                .parse::<GameRecord>()
+                .unwrap_err()
+        };
+        broken("[Red \"", "[Red ");
+        broken("\"]\n[Blue", "]\n[Blue");
+        broken("[Position", "[Nothing");
+        broken("r6b/", "r7b/");
+        broken("2x2x2/8/b6r", "2x2x2/b6r");
+        broken("b6r r 0", "b6r g 0");
+        broken("[Result \"", "[Result \"x");
+        broken("blue forfeited (timeout)", "blue forfeited (boredom)");
+        broken("2. ", "3. ");
+        broken("score=-3", "score=lots");
+        broken("{first move}", "{first move");
+        broken("1. ", "1. z9 ");
+    }
+
+    #[test]
+    fn illegal_moves_are_refused() {
+        let mut record = game();
+        // far away from every blob
+        record.moves.insert(1, "d4".parse().unwrap());
+        let board = record.board();
+        let error = record.replay(&board).err().unwrap();
+        assert!(error.contains("illegal move d4 at ply 2"), "{}", error);
+    }
+
+    #[test]
+    fn unforced_passes_are_refused() {
+        let mut record = game();
+        record.moves.insert(0, RecordedMove::default());
+        let board = record.board();
+        let error = record.replay(&board).err().unwrap();
+        assert!(error.contains("illegal pass at ply 1"), "{}", error);
+    }
+
+    #[test]
+    fn wrong_results_are_refused() {
+        let mut record = game();
+        record.result = record.result.map(|r| r + 2);
+        let board = record.board();
+        assert!(record
+            .replay(&board)
+            .err()
+            .unwrap()
+            .contains("does not match"));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(-1), "1969-12-31");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(19_489), "2023-05-12");
+        assert_eq!(today().len(), 10);
+    }
+}
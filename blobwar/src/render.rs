@@ -0,0 +1,193 @@
+//! Text rendering of configurations, into any `fmt::Write` or `io::Write`.
+//!
+//! Three styles are available : plain ascii (for files and pipes), ascii with ANSI colours and
+//! unicode glyphs with ANSI colours. `Style::detect` picks one for the standard output.
+//! Markers can highlight the last move (source and destination) and the cells it flipped.
+use std::env;
+use std::fmt;
+use std::io;
+
+use crate::configuration::{Configuration, Movement};
+use crate::positions::{BoardPosition, Position, Positions};
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// How to draw a configuration.
+pub enum Style {
+    /// Ascii only, no escape codes : `#` holes, `r` red blobs, `b` blue blobs.
+    Plain,
+    /// Ascii with ANSI colours.
+    Ansi,
+    /// Unicode glyphs and box drawing with ANSI colours.
+    Unicode,
+}
+
+impl Style {
+    /// Best style for the standard output : plain when it is not a terminal (or when
+    /// `NO_COLOR` is set), unicode when the locale is utf-8 and ansi otherwise.
+    pub fn detect() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let tty = unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+        #[cfg(target_arch = "wasm32")]
+        let tty = false;
+        let dumb = env::var("TERM").map_or(true, |t| t == "dumb");
+        if !tty || dumb || env::var_os("NO_COLOR").is_some() {
+            return Style::Plain;
+        }
+        let utf8 = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|v| env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .is_some_and(|v| v.to_uppercase().replace('-', "").contains("UTF8"));
+        if utf8 {
+            Style::Unicode
+        } else {
+            Style::Ansi
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+/// Cells to highlight.
+pub struct Markers {
+    pub(crate) source: Option<Position>,
+    pub(crate) destination: Option<Position>,
+    pub(crate) flipped: Positions,
+}
+
+impl Markers {
+    /// Markers for `movement` played in configuration `before`.
+    pub fn new(before: &Configuration, movement: &Option<Movement>) -> Self {
+        let movement = match movement {
+            Some(movement) => movement,
+            None => return Markers::default(),
+        };
+        let after = before.play(movement);
+        let mover = before.current_player as usize;
+        let (source, destination) = match *movement {
+            Movement::Duplicate(destination) => (None, destination),
+            Movement::Jump(source, destination) => (Some(source), destination),
+        };
+        Markers {
+            source,
+            destination: Some(destination),
+            flipped: after.blobs()[mover].intersection_with(before.blobs()[1 - mover]),
+        }
+    }
+}
+
+/// What is on a cell.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Cell {
+    Hole,
+    Red,
+    Blue,
+    Empty,
+}
+
+/// How a cell is highlighted.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Marker {
+    None,
+    Source,
+    Destination,
+    Flipped,
+}
+
+impl Style {
+    fn glyph(self, cell: Cell) -> &'static str {
+        match (self, cell) {
+            (Style::Plain, Cell::Hole) | (Style::Ansi, Cell::Hole) => "#",
+            (Style::Plain, Cell::Red) => "r",
+            (Style::Plain, Cell::Blue) => "b",
+            (Style::Ansi, Cell::Red) => "\x1b[31mx",
+            (Style::Ansi, Cell::Blue) => "\x1b[36mo",
+            (Style::Unicode, Cell::Hole) => "\x1b[90m▒",
+            (Style::Unicode, Cell::Red) => "\x1b[31m●",
+            (Style::Unicode, Cell::Blue) => "\x1b[36m○",
+            (Style::Unicode, Cell::Empty) => "·",
+            (_, Cell::Empty) => ".",
+        }
+    }
+
+    /// Text written around a cell, before and after its glyph.
+    fn marker(self, marker: Marker) -> (&'static str, &'static str) {
+        match (self, marker) {
+            (_, Marker::None) => ("", " "),
+            (Style::Plain, Marker::Source) => ("", "-"),
+            (Style::Plain, Marker::Destination) => ("", "*"),
+            (Style::Plain, Marker::Flipped) => ("", "+"),
+            (_, Marker::Source) => ("\x1b[4m", " "),
+            (_, Marker::Destination) => ("\x1b[1;7m", " "),
+            (_, Marker::Flipped) => ("\x1b[1;43m", " "),
+        }
+    }
+
+    fn frame(self) -> [&'static str; 5] {
+        match self {
+            Style::Unicode => ["┌", "┐", "└", "┘", "│"],
+            _ => ["+", "+", "+", "+", "|"],
+        }
+    }
+}
+
+/// Write `configuration` in given `style`, highlighting `markers`.
+pub fn render<W: fmt::Write>(
+    output: &mut W,
+    configuration: &Configuration,
+    style: Style,
+    markers: &Markers,
+) -> fmt::Result {
+    let [top_left, top_right, bottom_left, bottom_right, side] = style.frame();
+    let line = if style == Style::Unicode { "─" } else { "-" }.repeat(16);
+    writeln!(output, "   a b c d e f g h")?;
+    writeln!(output, "  {}{}{}", top_left, line, top_right)?;
+    let holes = configuration.board().holes;
+    let blobs = configuration.blobs();
+    for y in 0..8 {
+        write!(output, "{} {}", y + 1, side)?;
+        for x in 0..8 {
+            let position = Position::from_2d(x, y);
+            let cell = if holes.contains(position) {
+                Cell::Hole
+            } else if blobs[0].contains(position) {
+                Cell::Red
+            } else if blobs[1].contains(position) {
+                Cell::Blue
+            } else {
+                Cell::Empty
+            };
+            let marker = if markers.destination == Some(position) {
+                Marker::Destination
+            } else if markers.source == Some(position) {
+                Marker::Source
+            } else if markers.flipped.contains(position) {
+                Marker::Flipped
+            } else {
+                Marker::None
+            };
+            let (before, after) = style.marker(marker);
+            let glyph = style.glyph(cell);
+            write!(output, "{}{}", before, glyph)?;
+            if !before.is_empty() || glyph.starts_with('\x1b') {
+                write!(output, "{}", RESET)?;
+            }
+            write!(output, "{}", after)?;
+        }
+        writeln!(output, "{}", side)?;
+    }
+    write!(output, "  {}{}{}", bottom_left, line, bottom_right)
+}
+
+/// Write `configuration` into an `io::Write` (see `render`).
+pub fn render_io<W: io::Write>(
+    output: &mut W,
+    configuration: &Configuration,
+    style: Style,
+    markers: &Markers,
+) -> io::Result<()> {
+    let mut text = String::new();
+    render(&mut text, configuration, style, markers).expect("writing into a string failed");
+    writeln!(output, "{}", text)
+}
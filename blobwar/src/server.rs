@@ -0,0 +1,278 @@
+//! Match server : play a series of games between two players, local engines or remote clients.
+//!
+//! Colours alternate every game and boards change every two games, so that each player gets
+//! both colours on each board. Each player can be given a clock : a total thinking time per
+//! game, running out of it forfeits the game.
+use std::fmt;
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::broadcast::Broadcast;
+use crate::configuration::{Configuration, Movement};
+use crate::elo::Score;
+use crate::notation::Fen;
+use crate::record::{ForfeitReason, GameRecord, Termination};
+use crate::session::Sessions;
+use crate::strategy::registry::DEFAULT_PORT;
+use crate::strategy::{EngineSpec, NetworkPlayer, Registry, Request, Strategy};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Who plays : a local engine or a remote client.
+pub enum PlayerSpec {
+    /// Engine built from the `Registry`.
+    Local(EngineSpec),
+    /// Client connecting to the server.
+    Remote,
+}
+
+impl FromStr for PlayerSpec {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        if string == "remote" {
+            Ok(PlayerSpec::Remote)
+        } else {
+            string.parse().map(PlayerSpec::Local)
+        }
+    }
+}
+
+impl fmt::Display for PlayerSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayerSpec::Local(spec) => write!(f, "{}", spec),
+            PlayerSpec::Remote => write!(f, "remote"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Match settings.
+pub struct MatchSettings {
+    /// Address to listen on for remote players.
+    pub bind: String,
+    /// Port to listen on for remote players.
+    pub port: u16,
+    /// The two players.
+    pub players: [PlayerSpec; 2],
+    /// Number of games.
+    pub games: usize,
+    /// Boards (files or positions) played in turn.
+    pub boards: Vec<String>,
+    /// Thinking time of each player for each game.
+    pub clock: Option<Duration>,
+    /// Remote players speak the legacy protocol.
+    pub legacy: bool,
+    /// Directory where game records are saved.
+    pub records: Option<PathBuf>,
+    /// Port to broadcast games on for spectators.
+    pub broadcast: Option<u16>,
+    /// Time remote players have to reconnect after losing their connection.
+    pub grace: Option<Duration>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            bind: "0.0.0.0".to_owned(),
+            port: DEFAULT_PORT,
+            players: [
+                PlayerSpec::Local("alphabeta:time=1000".parse().unwrap()),
+                PlayerSpec::Remote,
+            ],
+            games: 1,
+            boards: vec!["standard".to_owned()],
+            clock: None,
+            legacy: false,
+            records: None,
+            broadcast: None,
+            grace: None,
+        }
+    }
+}
+
+/// A player of the match.
+pub(crate) enum Player {
+    Local(Box<dyn Strategy>),
+    Remote(Box<NetworkPlayer>),
+}
+
+/// A player with its clock.
+pub(crate) struct Clocked {
+    pub(crate) player: Player,
+    clock: Option<Duration>,
+    remaining: Duration,
+    request: Option<Request>,
+}
+
+impl Clocked {
+    pub(crate) fn new(player: Player, clock: Option<Duration>) -> Self {
+        Clocked {
+            player,
+            clock,
+            remaining: clock.unwrap_or_default(),
+            request: None,
+        }
+    }
+
+    fn strategy(&mut self) -> &mut dyn Strategy {
+        match &mut self.player {
+            Player::Local(strategy) => strategy,
+            Player::Remote(player) => &mut **player,
+        }
+    }
+}
+
+impl fmt::Display for Clocked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.player {
+            Player::Local(strategy) => write!(f, "{}", strategy),
+            Player::Remote(player) => write!(f, "{}", player),
+        }
+    }
+}
+
+impl Strategy for Clocked {
+    fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
+        if let (Player::Remote(player), Some(_)) = (&self.player, self.clock) {
+            // a zero timeout is refused, a millisecond does as well
+            let timeout = self.remaining.max(Duration::from_millis(1));
+            if let Err(e) = player.set_timeout(Some(timeout)) {
+                eprintln!("failed setting remote timeout: {}", e);
+            }
+        }
+        let start = Instant::now();
+        let movement = self.strategy().compute_next_move(configuration);
+        self.request = self.strategy().request();
+        if self.clock.is_some() {
+            let elapsed = start.elapsed();
+            if elapsed > self.remaining {
+                self.remaining = Duration::default();
+                self.request = Some(Request::Forfeit(ForfeitReason::Timeout));
+                return None;
+            }
+            self.remaining -= elapsed;
+        }
+        movement
+    }
+
+    fn request(&mut self) -> Option<Request> {
+        self.request.take()
+    }
+
+    fn game_started(&mut self, start: &Configuration) {
+        self.strategy().game_started(start)
+    }
+
+    fn game_over(&mut self, end: &Configuration, termination: Option<&Termination>) {
+        self.remaining = self.clock.unwrap_or_default();
+        self.strategy().game_over(end, termination)
+    }
+}
+
+/// Result of a match.
+pub struct MatchResult {
+    /// Score of the first player.
+    pub score: Score,
+    /// All games.
+    pub records: Vec<GameRecord>,
+}
+
+/// Play the match described by `settings`.
+pub fn play_match(settings: &MatchSettings) -> Result<MatchResult, String> {
+    if settings.boards.is_empty() {
+        return Err("no boards".to_owned());
+    }
+    let starts = settings
+        .boards
+        .iter()
+        .map(|board| Fen::load(board))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let registry = Registry::default();
+    let listener = if settings.players.contains(&PlayerSpec::Remote) {
+        let listener = TcpListener::bind((settings.bind.as_str(), settings.port))
+            .map_err(|e| format!("failed binding {}:{}: {}", settings.bind, settings.port, e))?;
+        Some(listener)
+    } else {
+        None
+    };
+    let mut broadcast = match settings.broadcast {
+        Some(port) => Some(
+            Broadcast::listen((settings.bind.as_str(), port))
+                .map_err(|e| format!("failed broadcasting on port {}: {}", port, e))?,
+        ),
+        None => None,
+    };
+    let sessions = settings.grace.map(Sessions::new);
+    let mut players = Vec::new();
+    for spec in &settings.players {
+        let player = match spec {
+            PlayerSpec::Local(spec) => Player::Local(registry.build(spec)?),
+            PlayerSpec::Remote => {
+                let listener = listener.as_ref().expect("no listener");
+                println!(
+                    "waiting for remote player on {}:{}",
+                    settings.bind, settings.port
+                );
+                let (stream, _) = listener.accept().map_err(|e| e.to_string())?;
+                let player = if settings.legacy {
+                    NetworkPlayer::legacy(stream)
+                } else {
+                    NetworkPlayer::new(stream)
+                }
+                .map_err(|e| e.to_string())?;
+                Player::Remote(Box::new(match &sessions {
+                    Some(sessions) => player.resumable(sessions).map_err(|e| e.to_string())?,
+                    None => player,
+                }))
+            }
+        };
+        players.push(Clocked::new(player, settings.clock));
+    }
+    // remote players reconnect on the same port
+    if let (Some(sessions), Some(listener)) = (&sessions, listener) {
+        sessions.serve(listener);
+    }
+    let (first, second) = players.split_at_mut(1);
+    let (first, second) = (&mut first[0], &mut second[0]);
+
+    if let Some(directory) = &settings.records {
+        fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    }
+    let mut result = MatchResult {
+        score: Score::default(),
+        records: Vec::new(),
+    };
+    for game in 0..settings.games {
+        let start = &starts[(game / 2) % starts.len()];
+        let board = start.board();
+        let mut configuration = start.configuration(&board);
+        // the first player is red in even games
+        let first_is_red = game % 2 == 0;
+        println!(
+            "game {} : {} is red",
+            game + 1,
+            if first_is_red { &*first } else { &*second }
+        );
+        let record = if first_is_red {
+            configuration.spectated_battle(&mut *first, &mut *second, &mut broadcast)
+        } else {
+            configuration.spectated_battle(&mut *second, &mut *first, &mut broadcast)
+        };
+        result.score.add(match record.winner() {
+            None => 0,
+            Some(blue_won) if blue_won != first_is_red => 1,
+            Some(_) => -1,
+        });
+        println!("after {} games : {}", game + 1, result.score);
+        if let Some(directory) = &settings.records {
+            let path = directory.join(format!("game_{:03}.txt", game + 1));
+            record.save(&path).map_err(|e| e.to_string())?;
+        }
+        result.records.push(record);
+    }
+    Ok(result)
+}
@@ -0,0 +1,135 @@
+//! Sessions of remote players, letting them reconnect after losing their connection.
+//!
+//! Each resumable `NetworkPlayer` opens a `Session` and sends its token to the client. Clients
+//! reconnecting say `hello` then `resume` with their token (see `protocol`) and their new
+//! connection is handed over to the player, which picks it up when it next needs the client.
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::protocol::{Connection, Message};
+
+/// All open sessions of a server.
+#[derive(Clone)]
+pub struct Sessions {
+    grace: Duration,
+    waiting: Arc<Mutex<HashMap<String, Sender<Connection>>>>,
+}
+
+/// A session, closed when dropped.
+pub struct Session {
+    token: String,
+    sessions: Sessions,
+    connections: Receiver<Connection>,
+}
+
+impl Sessions {
+    /// No sessions yet, clients get `grace` to reconnect.
+    pub fn new(grace: Duration) -> Self {
+        Sessions {
+            grace,
+            waiting: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// How long clients have to reconnect.
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// Open a new session with a random token.
+    pub fn open(&self) -> Session {
+        let mut rng = rand::thread_rng();
+        let token: String = (0..16)
+            .map(|_| format!("{:x}", rng.gen_range(0..16u8)))
+            .collect();
+        let (sender, connections) = channel();
+        self.waiting.lock().unwrap().insert(token.clone(), sender);
+        Session {
+            token,
+            sessions: self.clone(),
+            connections,
+        }
+    }
+
+    /// Hand given connection over to the session with given token.
+    /// The connection is given back if there is no such session.
+    pub fn resume(&self, token: &str, connection: Connection) -> Result<(), Connection> {
+        match self.waiting.lock().unwrap().get(token) {
+            Some(sender) => sender.send(connection).map_err(|e| e.0),
+            None => Err(connection),
+        }
+    }
+
+    /// Accept reconnecting clients on given listener (in a background thread).
+    pub fn serve(&self, listener: TcpListener) {
+        let sessions = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let sessions = sessions.clone();
+                thread::spawn(move || {
+                    if let Err(e) = stream.and_then(|stream| sessions.welcome(stream)) {
+                        eprintln!("failed resuming session: {}", e);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Greet a reconnecting client and give its connection to its session.
+    fn welcome(&self, stream: TcpStream) -> io::Result<()> {
+        let mut connection = Connection::new(stream)?;
+        connection.handshake("blobwar server")?;
+        let token = match connection.receive()? {
+            Message::Resume { token } => token,
+            other => {
+                let message = format!("expected resume, got {:?}", other);
+                connection.send(&Message::Error {
+                    message: message.clone(),
+                })?;
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        };
+        if !self.waiting.lock().unwrap().contains_key(&token) {
+            return connection.send(&Message::Error {
+                message: "unknown session".to_owned(),
+            });
+        }
+        connection.send(&Message::Session {
+            token: token.clone(),
+            grace: self.grace.as_millis() as u64,
+        })?;
+        // the session may have closed in between, nothing left to resume then
+        let _ = self.resume(&token, connection);
+        Ok(())
+    }
+}
+
+impl Session {
+    /// Token to give to the client.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Wait at most `timeout` for the client to reconnect.
+    pub fn wait(&self, timeout: Duration) -> Option<Connection> {
+        self.connections.recv_timeout(timeout).ok()
+    }
+
+    /// Connection of a client which reconnected already, if any.
+    pub fn reconnected(&self) -> Option<Connection> {
+        self.connections.try_recv().ok()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.sessions.waiting.lock().unwrap().remove(&self.token);
+    }
+}
@@ -1,10 +1,12 @@
 //! Provide methods sharing a `Movement` in shared memory between processes.
-//! Slave anytime process writes new moves atomically and master process only reads them.
+//! Slave anytime process writes new moves (with the depth reached and their score) and master
+//! process only reads them, through a `#[repr(C)]` layout guarded by seqlocks.
 use super::configuration::Movement;
+use super::strategy::iterative::Progress;
 use libc::off_t;
 use libc::{c_void, size_t};
 use nix;
-use nix::fcntl::{O_CREAT, O_RDWR};
+use nix::fcntl::{O_CREAT, O_EXCL, O_RDWR};
 use nix::sys::mman::MAP_SHARED;
 use nix::sys::mman::{mmap, munmap};
 use nix::sys::mman::{shm_open, shm_unlink};
@@ -12,17 +14,98 @@ use nix::sys::mman::{PROT_READ, PROT_WRITE};
 use nix::sys::stat::{S_IRUSR, S_IWUSR};
 use nix::unistd::{close, ftruncate};
 use std::os::unix::io::RawFd;
+use std::process;
 use std::ptr;
 
+use std::hint::spin_loop;
 use std::mem;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{fence, AtomicI8, AtomicU32, AtomicU8, AtomicUsize, Ordering};
 
+/// Number of segments created so far by this process, making their names unique.
+static SEGMENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Kinds of published moves, zeroed slots hold nothing.
+const PASS: u8 = 1;
+const DUPLICATE: u8 = 2;
+const JUMP: u8 = 3;
+
+/// Stored score of results without any, real scores never reach it.
+const NO_SCORE: i8 = i8::MIN;
+
+/// One published result, guarded by a seqlock.
+#[repr(C)]
+struct Slot {
+    /// Even when the slot is consistent, odd while it is being written.
+    sequence: AtomicU32,
+    kind: AtomicU8,
+    source: AtomicU8,
+    destination: AtomicU8,
+    depth: AtomicU8,
+    score: AtomicI8,
+}
+
+/// Layout of the shared memory. The writer fills the slot which is not the latest one, then
+/// publishes it : a writer killed in the middle of a store leaves the latest slot intact.
+#[repr(C)]
 struct InnerAtomicMove {
-    movements: [Option<Movement>; 2],
-    selected: AtomicUsize,
+    /// Number of stores so far, the latest result is in `slots[latest % 2]`.
+    latest: AtomicU32,
+    slots: [Slot; 2],
+}
+
+impl Slot {
+    fn write(&self, progress: &Progress) {
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        self.sequence
+            .store(sequence.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        let (kind, source, destination) = match progress.movement {
+            None => (PASS, 0, 0),
+            Some(Movement::Duplicate(destination)) => (DUPLICATE, 0, destination),
+            Some(Movement::Jump(source, destination)) => (JUMP, source, destination),
+        };
+        self.kind.store(kind, Ordering::Relaxed);
+        self.source.store(source, Ordering::Relaxed);
+        self.destination.store(destination, Ordering::Relaxed);
+        self.depth.store(progress.depth, Ordering::Relaxed);
+        self.score
+            .store(progress.score.unwrap_or(NO_SCORE), Ordering::Relaxed);
+        self.sequence
+            .store(sequence.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Consistent content of the slot, `None` if it is being written.
+    fn read(&self) -> Option<Option<Progress>> {
+        let sequence = self.sequence.load(Ordering::Acquire);
+        if sequence % 2 == 1 {
+            return None;
+        }
+        let kind = self.kind.load(Ordering::Relaxed);
+        let source = self.source.load(Ordering::Relaxed);
+        let destination = self.destination.load(Ordering::Relaxed);
+        let depth = self.depth.load(Ordering::Relaxed);
+        let score = self.score.load(Ordering::Relaxed);
+        fence(Ordering::Acquire);
+        if self.sequence.load(Ordering::Relaxed) != sequence {
+            return None;
+        }
+        let movement = match kind {
+            PASS => None,
+            DUPLICATE => Some(Movement::Duplicate(destination)),
+            JUMP => Some(Movement::Jump(source, destination)),
+            // nothing published yet
+            _ => return Some(None),
+        };
+        Some(Some(Progress {
+            movement,
+            depth,
+            score: Some(score).filter(|&score| score != NO_SCORE),
+        }))
+    }
 }
 
 pub struct AtomicMove {
+    name: String,
     fd: RawFd,
     created: bool,
     address: *mut c_void,
@@ -31,19 +114,40 @@ pub struct AtomicMove {
 impl Drop for AtomicMove {
     fn drop(&mut self) {
         let size = mem::size_of::<InnerAtomicMove>();
-        close(self.fd).unwrap();
-        munmap(self.address, size as size_t).unwrap();
+        if let Err(e) = close(self.fd) {
+            eprintln!("failed closing shmem {} : {}", self.name, e);
+        }
+        if !self.address.is_null() {
+            if let Err(e) = munmap(self.address, size as size_t) {
+                eprintln!("failed unmapping shmem {} : {}", self.name, e);
+            }
+        }
 
         if self.created {
-            shm_unlink("blobwar").unwrap();
+            if let Err(e) = shm_unlink(self.name.as_str()) {
+                eprintln!("failed unlinking shmem {} : {}", self.name, e);
+            }
         }
     }
 }
 
 impl AtomicMove {
+    /// Create a new segment, under a name no other search uses.
     pub fn new() -> Result<Self, nix::Error> {
         let size = mem::size_of::<InnerAtomicMove>();
-        let fd = shm_open("blobwar", O_CREAT | O_RDWR, S_IRUSR | S_IWUSR)?;
+        let name = format!(
+            "blobwar-{}-{}",
+            process::id(),
+            SEGMENTS.fetch_add(1, Ordering::Relaxed)
+        );
+        let fd = shm_open(name.as_str(), O_CREAT | O_EXCL | O_RDWR, S_IRUSR | S_IWUSR)?;
+        // from now on dropping unlinks the segment, even on errors
+        let mut atomic_move = AtomicMove {
+            name,
+            fd,
+            address: ptr::null_mut(),
+            created: true,
+        };
         ftruncate(fd, size as off_t)?;
 
         let address = mmap(
@@ -54,23 +158,16 @@ impl AtomicMove {
             fd,
             0,
         )?;
+        atomic_move.address = address;
 
-        let atomic: &mut InnerAtomicMove =
-            unsafe { (address as *mut InnerAtomicMove).as_mut().unwrap() };
-        atomic.movements[0] = None;
-        atomic.movements[1] = None;
-        atomic.selected = Default::default();
-
-        Ok(AtomicMove {
-            fd,
-            address,
-            created: true,
-        })
+        // new segments are zeroed : nothing published yet
+        Ok(atomic_move)
     }
 
-    pub fn connect() -> Result<Self, nix::Error> {
+    /// Connect to the segment of given name, created by another process.
+    pub fn connect(name: &str) -> Result<Self, nix::Error> {
         let size = mem::size_of::<InnerAtomicMove>();
-        let fd = shm_open("blobwar", O_RDWR, S_IRUSR | S_IWUSR)?;
+        let fd = shm_open(name, O_RDWR, S_IRUSR | S_IWUSR)?;
         let address = mmap(
             ptr::null_mut(),
             size as size_t,
@@ -80,23 +177,119 @@ impl AtomicMove {
             0,
         )?;
         Ok(AtomicMove {
+            name: name.to_owned(),
             fd,
             address,
             created: false,
         })
     }
 
-    pub fn store(&mut self, movement: Option<Movement>) {
-        let atomic: &mut InnerAtomicMove =
-            unsafe { (self.address as *mut InnerAtomicMove).as_mut().unwrap() };
-        let index = atomic.selected.load(Ordering::SeqCst);
-        atomic.movements[(index + 1) % 2] = movement;
-        atomic.selected.fetch_add(1, Ordering::SeqCst);
+    /// Name to give to `connect`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn inner(&self) -> &InnerAtomicMove {
+        unsafe { (self.address as *const InnerAtomicMove).as_ref().unwrap() }
+    }
+
+    /// Publish a new result (only one process may store).
+    pub fn store(&mut self, progress: Progress) {
+        let inner = self.inner();
+        let latest = inner.latest.load(Ordering::Relaxed);
+        let next = latest.wrapping_add(1);
+        inner.slots[next as usize % 2].write(&progress);
+        inner.latest.store(next, Ordering::Release);
+    }
+
+    /// Latest published result, if any.
+    pub fn load(&self) -> Option<Progress> {
+        let inner = self.inner();
+        loop {
+            // the writer may move on to our slot while we read it, try again then
+            let latest = inner.latest.load(Ordering::Acquire);
+            if let Some(progress) = inner.slots[latest as usize % 2].read() {
+                return progress;
+            }
+            spin_loop();
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_get_their_own_segments() {
+        let mut first = AtomicMove::new().unwrap();
+        let second = AtomicMove::new().unwrap();
+        assert_ne!(first.name(), second.name());
+        assert!(first
+            .name()
+            .starts_with(&format!("blobwar-{}-", process::id())));
+        // names are never reused
+        assert!(shm_open(second.name(), O_CREAT | O_EXCL | O_RDWR, S_IRUSR | S_IWUSR).is_err());
+
+        let progress = Progress {
+            movement: Some(Movement::Duplicate(9)),
+            depth: 2,
+            score: Some(1),
+        };
+        first.store(progress);
+        assert_eq!(second.load(), None);
+        let reader = AtomicMove::connect(first.name()).unwrap();
+        assert_eq!(reader.load(), Some(progress));
+        drop(reader);
+
+        // only the creator unlinks the segment
+        let name = first.name().to_owned();
+        assert!(AtomicMove::connect(&name).is_ok());
+        drop(first);
+        assert!(AtomicMove::connect(&name).is_err());
+    }
+
+    #[test]
+    fn slots_round_trip() {
+        let slot = Slot {
+            sequence: AtomicU32::new(0),
+            kind: AtomicU8::new(0),
+            source: AtomicU8::new(0),
+            destination: AtomicU8::new(0),
+            depth: AtomicU8::new(0),
+            score: AtomicI8::new(0),
+        };
+        assert_eq!(slot.read(), Some(None));
+        let results = [
+            Progress {
+                movement: None,
+                depth: 1,
+                score: Some(-3),
+            },
+            Progress {
+                movement: Some(Movement::Duplicate(9)),
+                depth: 2,
+                score: Some(0),
+            },
+            Progress {
+                movement: Some(Movement::Jump(0, 16)),
+                depth: 3,
+                score: Some(i8::MAX),
+            },
+            Progress {
+                movement: Some(Movement::Jump(63, 45)),
+                depth: 4,
+                score: None,
+            },
+        ];
+        for progress in &results {
+            slot.write(progress);
+            assert_eq!(slot.read(), Some(Some(*progress)));
+        }
+        assert_eq!(slot.score.load(Ordering::Relaxed), NO_SCORE);
 
-    pub fn load(&self) -> Option<Movement> {
-        let atomic: &InnerAtomicMove =
-            unsafe { (self.address as *const InnerAtomicMove).as_ref().unwrap() };
-        atomic.movements[atomic.selected.load(Ordering::SeqCst) % 2]
+        // a slot being written is never read
+        slot.sequence.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(slot.read(), None);
     }
 }
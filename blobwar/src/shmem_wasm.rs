@@ -0,0 +1,25 @@
+//! Stand-in for the shared memory on wasm targets : there are no processes to share moves
+//! with, so anytime algorithms and iterative deepening are not available.
+use super::strategy::iterative::Progress;
+
+pub struct AtomicMove;
+
+impl AtomicMove {
+    pub fn new() -> Result<Self, String> {
+        Err("no shared memory on wasm".to_owned())
+    }
+
+    pub fn connect(_name: &str) -> Result<Self, String> {
+        Err("no shared memory on wasm".to_owned())
+    }
+
+    pub fn name(&self) -> &str {
+        ""
+    }
+
+    pub fn store(&mut self, _progress: Progress) {}
+
+    pub fn load(&self) -> Option<Progress> {
+        None
+    }
+}
@@ -1,19 +1,25 @@
 //! Alpha - Beta algorithm
 use std::fmt;
 
-use super::{BenchmarkUnitaire, Strategy};
+use super::{BenchmarkUnitaire, Progress, Strategy};
 use crate::configuration::{Configuration, Movement};
 use crate::shmem::AtomicMove;
 
 /// Anytime alpha beta algorithm.
 /// Any time algorithms will compute until a deadline is hit and the process is killed.
-/// They are therefore run in another process and communicate through shared memory.
+/// They are therefore run in another process and communicate through the shared memory
+/// segment of given name.
 /// This function is intended to be called from blobwar_iterative_deepening.
-pub fn alpha_beta_anytime(state: &Configuration) {
-    let mut movement = AtomicMove::connect().expect("failed connecting to shmem");
+pub fn alpha_beta_anytime(state: &Configuration, segment: &str) {
+    let mut shared = AtomicMove::connect(segment).expect("failed connecting to shmem");
     for depth in 1..100 {
-        let chosen_movement = AlphaBeta(depth).compute_next_move(state);
-        movement.store(chosen_movement);
+        let (movement, score) = AlphaBeta(depth).search(state);
+        // the search values the loss of the side to move, the score is its gain
+        shared.store(Progress {
+            movement,
+            depth,
+            score: Some(-score),
+        });
     }
 }
 
@@ -28,9 +34,7 @@ impl fmt::Display for AlphaBeta {
 
 impl Strategy for AlphaBeta {
     fn compute_next_move(&mut self, state: &Configuration) -> Option<Movement> {
-        let (alpha, beta) = (i8::MIN, i8::MIN);
-        let (movement, _) = AlphaBeta::alphabeta(self, state, self.0, alpha, beta, false);
-        movement
+        self.search(state).0
     }
 }
 
@@ -41,6 +45,14 @@ impl BenchmarkUnitaire for AlphaBeta {
 }
 
 impl AlphaBeta {
+    /// Best move with its value.
+    fn search(&mut self, state: &Configuration) -> (Option<Movement>, i8) {
+        // alpha and beta are what each side is sure to get : nothing yet is the full window, so
+        // the value found at the root is exact
+        let (alpha, beta) = (i8::MIN, i8::MIN);
+        AlphaBeta::alphabeta(self, state, self.0, alpha, beta, false)
+    }
+
     fn alphabeta(
         &mut self,
         state: &Configuration,
@@ -1,19 +1,25 @@
 //! Alpha - Beta algorithm with Pass heuristic
 use std::fmt;
 
-use super::{BenchmarkUnitaire, Strategy};
+use super::{BenchmarkUnitaire, Progress, Strategy};
 use crate::configuration::{Configuration, Movement};
 use crate::shmem::AtomicMove;
 
 /// Anytime alpha beta algorithm.
 /// Any time algorithms will compute until a deadline is hit and the process is killed.
-/// They are therefore run in another process and communicate through shared memory.
+/// They are therefore run in another process and communicate through the shared memory
+/// segment of given name.
 /// This function is intended to be called from blobwar_iterative_deepening.
-pub fn alpha_beta_pass_anytime(state: &Configuration) {
-    let mut movement = AtomicMove::connect().expect("failed connecting to shmem");
+pub fn alpha_beta_pass_anytime(state: &Configuration, segment: &str) {
+    let mut shared = AtomicMove::connect(segment).expect("failed connecting to shmem");
     for depth in 5..100 {
-        let chosen_movement = AlphaBetaPass(depth).compute_next_move(state);
-        movement.store(chosen_movement);
+        let (movement, score) = AlphaBetaPass(depth).search(state);
+        // searches return a loss for the side to move : report its opposite
+        shared.store(Progress {
+            movement,
+            depth,
+            score: Some(-score),
+        });
     }
 }
 
@@ -34,13 +40,19 @@ impl fmt::Display for AlphaBetaPass {
 
 impl Strategy for AlphaBetaPass {
     fn compute_next_move(&mut self, state: &Configuration) -> Option<Movement> {
-        let (alpha, beta) = (i8::MIN, i8::MIN);
-        let (movement, _) = AlphaBetaPass::alphabetaPass(self, state, self.0, alpha, beta, false);
-        movement
+        self.search(state).0
     }
 }
 
 impl AlphaBetaPass {
+    /// Best move with its value.
+    fn search(&mut self, state: &Configuration) -> (Option<Movement>, i8) {
+        // neither side is guaranteed anything at first : the root is searched with the full
+        // window and its value is exact
+        let (alpha, beta) = (i8::MIN, i8::MIN);
+        AlphaBetaPass::alphabetaPass(self, state, self.0, alpha, beta, false)
+    }
+
     fn alphabetaPass(
         &mut self,
         state: &Configuration,
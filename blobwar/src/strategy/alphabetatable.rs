@@ -2,7 +2,7 @@
 use std::collections::HashMap;
 use std::fmt;
 
-use super::{BenchmarkUnitaire, Strategy};
+use super::{BenchmarkUnitaire, Progress, Strategy};
 use crate::configuration::{Configuration, Movement};
 use crate::shmem::AtomicMove;
 use rand::Rng;
@@ -52,13 +52,19 @@ impl TranspositionTable {
 
 /// Anytime alpha beta algorithm.
 /// Any time algorithms will compute until a deadline is hit and the process is killed.
-/// They are therefore run in another process and communicate through shared memory.
+/// They are therefore run in another process and communicate through the shared memory
+/// segment of given name.
 /// This function is intended to be called from blobwar_iterative_deepening.
-pub fn alpha_beta_table_anytime(state: &Configuration) {
-    let mut movement = AtomicMove::connect().expect("failed connecting to shmem");
+pub fn alpha_beta_table_anytime(state: &Configuration, segment: &str) {
+    let mut shared = AtomicMove::connect(segment).expect("failed connecting to shmem");
     for depth in 1..100 {
-        let chosen_movement = AlphaBetaTable(depth).compute_next_move(state);
-        movement.store(chosen_movement);
+        let (movement, score) = AlphaBetaTable(depth).search(state);
+        // like `Configuration::value` the value is a loss, scores are gains for the side to move
+        shared.store(Progress {
+            movement,
+            depth,
+            score: Some(-score),
+        });
     }
 }
 
@@ -73,9 +79,18 @@ impl fmt::Display for AlphaBetaTable {
 
 impl Strategy for AlphaBetaTable {
     fn compute_next_move(&mut self, state: &Configuration) -> Option<Movement> {
+        self.search(state).0
+    }
+}
+
+impl AlphaBetaTable {
+    /// Best move with its value.
+    fn search(&mut self, state: &Configuration) -> (Option<Movement>, i8) {
         let zobrist_table = generate_zobrist_table();
+        // starting from nothing guaranteed to either side is the full window : the root value is
+        // exact, only deeper values are bounds
         let (alpha, beta) = (i8::MIN, i8::MIN);
-        let (movement, _) = AlphaBetaTable::alphabeta_transposition_table(
+        AlphaBetaTable::alphabeta_transposition_table(
             self,
             state,
             self.0,
@@ -84,12 +99,9 @@ impl Strategy for AlphaBetaTable {
             false,
             &mut TranspositionTable::new(),
             &zobrist_table,
-        );
-        movement
+        )
     }
-}
 
-impl AlphaBetaTable {
     fn alphabeta_transposition_table(
         &mut self,
         state: &Configuration,
@@ -1,68 +1,126 @@
 //! Human player.
-use super::Strategy;
-use crate::configuration::{Configuration, Movement};
-use crate::positions::{BoardPosition, Position};
+use super::{EngineSpec, Request, Strategy};
+use crate::configuration::{parse_move, Configuration, Movement};
 use std::fmt;
 use std::io;
 use std::io::BufRead;
 
-/// Let a human enter moves on stdin.
-pub struct Human();
+const HELP: &str = "commands:
+  b2, a1-c3    play a duplication or a jump
+  moves        list legal moves
+  hint         ask the hint engine for a move
+  undo         take back your last move
+  resign       give up the game
+  save FILE    save the game so far
+  help         show this message";
+
+/// Let a human enter moves and commands on stdin.
+pub struct Human {
+    hint: EngineSpec,
+    request: Option<Request>,
+}
+
 impl fmt::Display for Human {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Human")
     }
 }
 
-fn ask_cell() -> Result<(u8, u8), io::Error> {
-    let mut line = String::new();
-    let stdin = io::stdin();
-    stdin.lock().read_line(&mut line)?;
-    let mut words = line.split_whitespace();
-    let y: u8 = words
-        .next()
-        .unwrap()
-        .parse()
-        .map_err(|_| io::ErrorKind::Other)?;
-    let x: u8 = words
-        .next()
-        .unwrap()
-        .parse()
-        .map_err(|_| io::ErrorKind::Other)?;
-    Ok((x, y))
+impl Default for Human {
+    fn default() -> Self {
+        Human::with_hint("alphabeta:depth=4".parse().unwrap())
+    }
 }
 
-fn ask_move() -> Result<Movement, io::Error> {
-    println!("enter start point");
-    let (sx, sy) = ask_cell()?;
-    let start_position = Position::from_2d(sx, sy);
-    println!("enter end point");
-    let (ex, ey) = ask_cell()?;
-    let end_position = Position::from_2d(ex, ey);
-    match start_position.distance_to(end_position) {
-        1 => Ok(Movement::Duplicate(end_position)),
-        2 => Ok(Movement::Jump(start_position, end_position)),
-        _ => {
-            println!("invalid movement");
-            ask_move()
+impl Human {
+    /// Human getting hints from given engine.
+    pub fn with_hint(hint: EngineSpec) -> Self {
+        Human {
+            hint,
+            request: None,
         }
     }
+
+    /// Ask the hint engine what to play.
+    fn hint(&self, configuration: &Configuration) {
+        match self.hint.build() {
+            Ok(mut engine) => match engine.compute_next_move(configuration) {
+                Some(movement) => println!("{} suggests {}", self.hint.label(), movement),
+                None => println!("{} suggests to pass", self.hint.label()),
+            },
+            Err(e) => println!("no hint available: {}", e),
+        }
+    }
+}
+
+/// Read a line on stdin, `None` if stdin is closed.
+fn read_line() -> Option<String> {
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
 }
 
 impl Strategy for Human {
     fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
-        if configuration.movements().next().is_some() {
-            loop {
-                if let Ok(movement) = ask_move() {
-                    if configuration.check_move(&movement) {
-                        return Some(movement);
-                    } else {
-                        println!("invalid movement (are you playing your color ?)");
-                    }
+        if configuration.movements().next().is_none() {
+            println!("no possible move, passing");
+            return None;
+        }
+        println!("enter your move or command (help for the list)");
+        loop {
+            let line = match read_line() {
+                Some(line) => line,
+                None => {
+                    println!("stdin closed, resigning");
+                    self.request = Some(Request::Resign);
+                    return None;
+                }
+            };
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (None, _) => continue,
+                (Some("help"), None) => println!("{}", HELP),
+                (Some("moves"), None) => println!(
+                    "{}",
+                    configuration
+                        .movements()
+                        .map(|m| m.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ),
+                (Some("hint"), None) => self.hint(configuration),
+                (Some("undo"), None) => {
+                    self.request = Some(Request::Undo);
+                    return None;
                 }
+                (Some("resign"), None) => {
+                    self.request = Some(Request::Resign);
+                    return None;
+                }
+                (Some("save"), Some(path)) => {
+                    self.request = Some(Request::Save(path.to_owned()));
+                    return None;
+                }
+                (Some("save"), None) => println!("usage: save FILE"),
+                (Some(word), None) => match parse_move(word) {
+                    Ok(None) => println!("you can only pass when you cannot move"),
+                    Ok(Some(movement)) if configuration.check_move(&movement) => {
+                        return Some(movement)
+                    }
+                    Ok(Some(movement)) => println!(
+                        "{} is not a legal move (are you playing your color ?)",
+                        movement
+                    ),
+                    Err(e) => println!("{} (help for the list of commands)", e),
+                },
+                (Some(_), Some(_)) => println!("too many words (help for the list of commands)"),
             }
-        } else {
-            None
         }
     }
+
+    fn request(&mut self) -> Option<Request> {
+        self.request.take()
+    }
 }
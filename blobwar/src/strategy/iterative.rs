@@ -2,32 +2,30 @@
 use std::fmt;
 
 use std::io;
-use std::process::Command;
+use std::process::{Child, Command};
 use std::thread::sleep;
 use std::time::Duration;
 
 use super::Strategy;
 use crate::configuration::{Configuration, Movement};
+use crate::notation::Fen;
 use crate::shmem::AtomicMove;
 
-#[derive(Copy, Clone, Debug)]
-/// All possible strategies for iterative deepening.
-pub enum IterativeStrategy {
-    /// MinMax algorithm
-    MinMax,
-    /// AlphaBeta algorithm
-    AlphaBeta,
-    /// AlphaBeta algorithm
-    AlphaBetaPass,
-    /// AlphaBetaTable algorithm
-    AlphaBetaTable,
-    /// MinMaxPar algorithm
-    MinMaxPar,
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Best move found so far by an anytime search.
+pub struct Progress {
+    /// Best move (`None` to pass).
+    pub movement: Option<Movement>,
+    /// Depth it was found at.
+    pub depth: u8,
+    /// Its value according to the search, `None` if the search only bounds it.
+    pub score: Option<i8>,
 }
 
 /// Anytime algorithms strategies. Implemented in another process.
+/// The algorithm is given by its name in the `Registry`, which must provide an anytime version.
 pub struct IterativeDeepening {
-    strategy: IterativeStrategy,
+    strategy: String,
     duration: u64,
 }
 
@@ -35,7 +33,7 @@ impl fmt::Display for IterativeDeepening {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "{:?} (iterative deepening {}ms)",
+            "{} (iterative deepening {}ms)",
             self.strategy, self.duration
         )
     }
@@ -43,30 +41,70 @@ impl fmt::Display for IterativeDeepening {
 
 impl Strategy for IterativeDeepening {
     fn compute_next_move(&mut self, state: &Configuration) -> Option<Movement> {
-        let movement = AtomicMove::new().expect("failed creating shmem");
-        let configuration = state.serialize();
-        let mut child = Command::new("blobwar_iterative_deepening")
-            .current_dir("./target/release/")
-            .arg(configuration)
-            .arg((self.strategy as usize).to_string())
-            .spawn()
-            .expect("failed to start child process");
+        let search = Search::start(&self.strategy, state).expect("failed to start search");
         sleep(Duration::from_millis(self.duration));
-        if let Err(e) = child.kill() {
-            if e.kind() != io::ErrorKind::InvalidInput {
-                panic!("failed to kill child")
-            }
+        search.stop().and_then(|progress| progress.movement)
+    }
+}
+
+/// Anytime search running in another process until stopped.
+/// Dropping it (even while unwinding) kills the process and removes the shared memory.
+pub struct Search {
+    child: Child,
+    movement: AtomicMove,
+}
+
+impl Search {
+    /// Start searching given position with given algorithm (see `IterativeDeepening`).
+    pub fn start(strategy: &str, state: &Configuration) -> io::Result<Self> {
+        let movement = AtomicMove::new().map_err(|e| io::Error::other(e.to_string()))?;
+        let position = Fen::from_configuration(state, 0).to_string();
+        let child = Command::new("blobwar_iterative_deepening")
+            .current_dir("./target/release/")
+            .arg(position)
+            .arg(strategy)
+            .arg(movement.name())
+            .spawn()?;
+        Ok(Search { child, movement })
+    }
+
+    /// Best move found so far, if any.
+    pub fn progress(&self) -> Option<Progress> {
+        self.movement.load()
+    }
+
+    /// Stop searching and return the best move found, if any.
+    pub fn stop(mut self) -> Option<Progress> {
+        self.kill();
+        self.movement.load()
+    }
+
+    /// Kill the search process if still running.
+    fn kill(&mut self) {
+        if let Ok(Some(_)) = self.child.try_wait() {
+            return;
+        }
+        if let Err(e) = self.child.kill() {
+            eprintln!("failed to kill search : {}", e);
         }
-        movement.load()
+        if let Err(e) = self.child.wait() {
+            eprintln!("failed to wait for search : {}", e);
+        }
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.kill();
     }
 }
 
 impl IterativeDeepening {
-    /// New iterative deepening strategy with given algorithm.
-    /// default time is 2 seconds.
-    pub fn new(strategy: IterativeStrategy) -> IterativeDeepening {
+    /// New iterative deepening strategy with given algorithm name.
+    /// default time is 1 second.
+    pub fn new(strategy: &str) -> IterativeDeepening {
         IterativeDeepening {
-            strategy,
+            strategy: strategy.to_owned(),
             duration: 1000,
         }
     }
@@ -74,7 +112,7 @@ impl IterativeDeepening {
     /// Sets duration in milliseconds on given algorithm.
     pub fn duration(&self, duration: u64) -> Self {
         IterativeDeepening {
-            strategy: self.strategy,
+            strategy: self.strategy.clone(),
             duration,
         }
     }
@@ -1,5 +1,5 @@
 //! Implementation of the min max algorithm.
-use super::{BenchmarkUnitaire, Strategy};
+use super::{BenchmarkUnitaire, Progress, Strategy};
 use crate::configuration::{Configuration, Movement};
 use crate::shmem::AtomicMove;
 use rayon::prelude::*;
@@ -16,13 +16,17 @@ impl BenchmarkUnitaire for MinMax {
 
 impl Strategy for MinMax {
     fn compute_next_move(&mut self, state: &Configuration) -> Option<Movement> {
-        //let (movement, _) = MinMax::min_max(self, state, self.0, state.current_player);
-
-        let (movement, _) = MinMax::min_max(self, state, self.0, false);
-        movement
+        self.search(state).0
     }
 }
 impl MinMax {
+    /// Best move with its value.
+    fn search(&mut self, state: &Configuration) -> (Option<Movement>, i8) {
+        //MinMax::min_max(self, state, self.0, state.current_player);
+
+        MinMax::min_max(self, state, self.0, false)
+    }
+
     /// Classic min-max algorithm
     fn min_max(
         &mut self,
@@ -128,11 +132,17 @@ impl fmt::Display for MinMax {
 
 /// Anytime min max algorithm.
 /// Any time algorithms will compute until a deadline is hit and the process is killed.
-/// They are therefore run in another process and communicate through shared memory.
+/// They are therefore run in another process and communicate through the shared memory
+/// segment of given name.
 /// This function is intended to be called from blobwar_iterative_deepening.
-pub fn min_max_anytime(state: &Configuration) {
-    let mut movement = AtomicMove::connect().expect("failed connecting to shmem");
+pub fn min_max_anytime(state: &Configuration, segment: &str) {
+    let mut shared = AtomicMove::connect(segment).expect("failed connecting to shmem");
     for depth in 1..100 {
-        movement.store(MinMax(depth).compute_next_move(state));
+        let (movement, score) = MinMax(depth).search(state);
+        shared.store(Progress {
+            movement,
+            depth,
+            score: Some(score),
+        });
     }
 }
@@ -1,5 +1,5 @@
 //! Implementation of the min max algorithm.
-use super::{BenchmarkUnitaire, Strategy};
+use super::{BenchmarkUnitaire, Progress, Strategy};
 use crate::configuration::{ Configuration, Movement };
 use crate::shmem::AtomicMove;
 use rayon::prelude::*;
@@ -16,12 +16,15 @@ impl BenchmarkUnitaire for MinMaxPar {
 
 impl Strategy for MinMaxPar {
     fn compute_next_move(&mut self, state: &Configuration) -> Option<Movement> {
-
-        let (movement, _) = MinMaxPar::min_max_par(self, state, self.0, false);
-        movement
+        self.search(state).0
     }
 }
 impl MinMaxPar {
+    /// Best move with its value.
+    fn search(&mut self, state: &Configuration) -> (Option<Movement>, i8) {
+        MinMaxPar::min_max_par(self, state, self.0, false)
+    }
+
     /// Parallel min-max algorithm
     fn min_max_par(&self, state: &Configuration, depth: u8, opposing_player:bool) -> (Option<Movement>, i8){
         if depth == 0 {
@@ -69,11 +72,17 @@ impl fmt::Display for MinMaxPar {
 
 /// Anytime min max parallel algorithm.
 /// Any time algorithms will compute until a deadline is hit and the process is killed.
-/// They are therefore run in another process and communicate through shared memory.
+/// They are therefore run in another process and communicate through the shared memory
+/// segment of given name.
 /// This function is intended to be called from blobwar_iterative_deepening.
-pub fn min_max_par_anytime(state: &Configuration) {
-    let mut movement = AtomicMove::connect().expect("failed connecting to shmem");
+pub fn min_max_par_anytime(state: &Configuration, segment: &str) {
+    let mut shared = AtomicMove::connect(segment).expect("failed connecting to shmem");
     for depth in 1..100 {
-        movement.store(MinMaxPar(depth).compute_next_move(state));
+        let (movement, score) = MinMaxPar(depth).search(state);
+        shared.store(Progress {
+            movement,
+            depth,
+            score: Some(score),
+        });
     }
 }
\ No newline at end of file
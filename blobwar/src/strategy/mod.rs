@@ -1,5 +1,6 @@
 //! We provide here structs for all possible kinds of players and AI.
 use crate::configuration::{Configuration, Movement};
+use crate::record::{ForfeitReason, Termination};
 use std::fmt;
 
 /// To be a strategy you need to be able to compute the next move.
@@ -7,6 +8,63 @@ pub trait Strategy: fmt::Display {
     /// Take current `Configuration` and return what to do next.
     /// None if no move is possible.
     fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement>;
+
+    /// Something the player asked for instead of moving (checked after each
+    /// `compute_next_move`). Only interactive players ever ask for anything.
+    fn request(&mut self) -> Option<Request> {
+        None
+    }
+
+    /// Called once before the first move with the starting `Configuration`. Only remote players
+    /// need to know.
+    fn game_started(&mut self, _start: &Configuration) {}
+
+    /// Called once the game is over with the final `Configuration` and how it ended if not on
+    /// the board. Only remote players need to know.
+    fn game_over(&mut self, _end: &Configuration, _termination: Option<&Termination>) {}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// What a player may ask for instead of moving.
+pub enum Request {
+    /// Take back our last move (and the adversary's reply).
+    Undo,
+    /// Give up the game.
+    Resign,
+    /// Save the game so far into given file.
+    Save(String),
+    /// We cannot go on (remote players only) and lose the game.
+    Forfeit(ForfeitReason),
+}
+
+impl<S: Strategy + ?Sized> Strategy for Box<S> {
+    fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
+        (**self).compute_next_move(configuration)
+    }
+    fn request(&mut self) -> Option<Request> {
+        (**self).request()
+    }
+    fn game_started(&mut self, start: &Configuration) {
+        (**self).game_started(start)
+    }
+    fn game_over(&mut self, end: &Configuration, termination: Option<&Termination>) {
+        (**self).game_over(end, termination)
+    }
+}
+
+impl<S: Strategy + ?Sized> Strategy for &mut S {
+    fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
+        (**self).compute_next_move(configuration)
+    }
+    fn request(&mut self) -> Option<Request> {
+        (**self).request()
+    }
+    fn game_started(&mut self, start: &Configuration) {
+        (**self).game_started(start)
+    }
+    fn game_over(&mut self, end: &Configuration, termination: Option<&Termination>) {
+        (**self).game_over(end, termination)
+    }
 }
 
 /// Create a new Strategy to then benchmark it
@@ -17,8 +75,16 @@ pub trait BenchmarkUnitaire: Strategy {
 
 pub mod human;
 pub use self::human::Human;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tui;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::tui::Tui;
 pub mod network;
 pub use self::network::NetworkPlayer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod process;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::process::ProcessEngine;
 pub mod greedy;
 pub use self::greedy::Greedy;
 pub mod minmax;
@@ -32,5 +98,8 @@ pub use self::alphabetapass::{alpha_beta_pass_anytime, AlphaBetaPass};
 pub mod alphabetatable;
 pub use self::alphabetatable::{alpha_beta_table_anytime, AlphaBetaTable};
 pub mod iterative;
-pub use self::iterative::IterativeDeepening;
-pub use self::iterative::IterativeStrategy;
+pub use self::iterative::{IterativeDeepening, Progress, Search};
+pub mod spec;
+pub use self::spec::EngineSpec;
+pub mod registry;
+pub use self::registry::Registry;
@@ -1,17 +1,55 @@
 //! Network player (server side)
-use super::Strategy;
+use super::{Request, Strategy};
 use crate::configuration::{Configuration, Movement};
+use crate::notation::Fen;
+use crate::protocol::{Color, Connection, Message};
+use crate::record::{ForfeitReason, Termination};
+use crate::session::{Session, Sessions};
 
 use serde_json::{de, Deserializer, StreamDeserializer};
+use std::cell::Cell;
 use std::fmt;
+use std::io;
 use std::io::prelude::*;
 use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// How we talk to the client.
+enum Link {
+    /// Versioned JSON messages (see `protocol`).
+    Messages {
+        connection: Connection,
+        /// Starting position of the game, until we send it in `new_game`.
+        start: Option<Fen>,
+    },
+    /// Serialized configurations one way, JSON `Option<Movement>` the other, in their derived
+    /// form of the first versions (`{"Jump":[3,17]}`, `{"Duplicate":9}` or `null`).
+    Legacy {
+        connection: TcpStream,
+        movements: StreamDeserializer<'static, de::IoRead<TcpStream>, Option<Movement>>,
+    },
+}
 
 /// Let a remote client enter moves.
+///
+/// Illegal moves, timeouts and disconnections make the client forfeit the game. Resumable
+/// players first give disconnected clients a grace period to reconnect.
 pub struct NetworkPlayer {
-    connection: TcpStream,
-    movements: StreamDeserializer<'static, de::IoRead<TcpStream>, Option<Movement>>,
+    link: Link,
     name: String,
+    request: Option<Request>,
+    /// Time the client has to answer.
+    timeout: Cell<Option<Duration>>,
+    /// Session and grace period of resumable players.
+    session: Option<(Session, Duration)>,
+}
+
+/// Forfeit reason for given failed read.
+fn read_failure(kind: io::ErrorKind) -> ForfeitReason {
+    match kind {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ForfeitReason::Timeout,
+        _ => ForfeitReason::Disconnection,
+    }
 }
 
 impl fmt::Display for NetworkPlayer {
@@ -21,26 +59,249 @@ impl fmt::Display for NetworkPlayer {
 }
 
 impl NetworkPlayer {
-    /// Create a new network player
-    pub fn new(data: TcpStream) -> Self {
-        let origin = data.peer_addr().unwrap().to_string();
-        let connection = data.try_clone().unwrap();
-        let movements = Deserializer::from_reader(data).into_iter::<Option<Movement>>();
-        NetworkPlayer {
+    /// Create a new network player, exchanging `hello` messages with the client.
+    pub fn new(data: TcpStream) -> io::Result<Self> {
+        let origin = data.peer_addr()?.to_string();
+        let mut connection = Connection::new(data)?;
+        let name = connection.handshake("blobwar server")?;
+        Ok(NetworkPlayer::from_connection(
             connection,
-            movements,
+            format!("{} ({})", name, origin),
+        ))
+    }
+
+    /// Network player on a connection where `hello` messages were already exchanged.
+    pub fn from_connection(connection: Connection, name: String) -> Self {
+        NetworkPlayer {
+            link: Link::Messages {
+                connection,
+                start: None,
+            },
+            name,
+            request: None,
+            timeout: Cell::new(None),
+            session: None,
+        }
+    }
+
+    /// Let the client reconnect within the grace period of `sessions` if it loses its
+    /// connection. Sends it its session token.
+    pub fn resumable(mut self, sessions: &Sessions) -> io::Result<Self> {
+        let session = sessions.open();
+        match &mut self.link {
+            Link::Messages { connection, .. } => connection.send(&Message::Session {
+                token: session.token().to_owned(),
+                grace: sessions.grace().as_millis() as u64,
+            })?,
+            Link::Legacy { .. } => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "legacy clients cannot resume",
+                ))
+            }
+        }
+        self.session = Some((session, sessions.grace()));
+        Ok(self)
+    }
+
+    /// Get the connection back (`None` for the legacy protocol), to play other games.
+    pub fn into_connection(self) -> Option<Connection> {
+        match self.link {
+            Link::Messages { connection, .. } => Some(connection),
+            Link::Legacy { .. } => None,
+        }
+    }
+
+    /// Create a new network player speaking the legacy protocol.
+    pub fn legacy(data: TcpStream) -> io::Result<Self> {
+        let origin = data.peer_addr()?.to_string();
+        let connection = data.try_clone()?;
+        let movements = Deserializer::from_reader(data).into_iter::<Option<Movement>>();
+        Ok(NetworkPlayer {
+            link: Link::Legacy {
+                connection,
+                movements,
+            },
             name: origin,
+            request: None,
+            timeout: Cell::new(None),
+            session: None,
+        })
+    }
+
+    /// Forfeit if the client takes longer than `timeout` to answer.
+    pub fn timeout(self, timeout: Duration) -> io::Result<Self> {
+        self.set_timeout(Some(timeout))?;
+        Ok(self)
+    }
+
+    /// Change the time the client has to answer (`None` to wait forever).
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let stream = match &self.link {
+            Link::Messages { connection, .. } => connection.stream(),
+            Link::Legacy { connection, .. } => connection,
+        };
+        stream.set_read_timeout(timeout)?;
+        self.timeout.set(timeout);
+        Ok(())
+    }
+
+    /// Ask for the next move, without validating it.
+    fn receive_move(
+        &mut self,
+        configuration: &Configuration,
+    ) -> Result<Option<Movement>, ForfeitReason> {
+        match &mut self.link {
+            Link::Legacy {
+                connection,
+                movements,
+            } => {
+                let mut message = configuration.serialize();
+                message.push('\n');
+                connection
+                    .write_all(message.into_bytes().as_slice())
+                    .map_err(|_| ForfeitReason::Disconnection)?;
+                match movements.next() {
+                    Some(Ok(movement)) => Ok(movement),
+                    Some(Err(e)) => Err(e
+                        .io_error_kind()
+                        .map_or(ForfeitReason::Disconnection, read_failure)),
+                    None => Err(ForfeitReason::Disconnection),
+                }
+            }
+            Link::Messages {
+                connection,
+                start: new_game,
+            } => {
+                let start = Instant::now();
+                loop {
+                    let clock = self
+                        .timeout
+                        .get()
+                        .map(|t| t.saturating_sub(start.elapsed()));
+                    if let Some(reconnected) = self.session.as_ref().and_then(|s| s.0.reconnected())
+                    {
+                        *connection = reconnected;
+                        connection
+                            .stream()
+                            .set_read_timeout(clock)
+                            .map_err(|_| ForfeitReason::Disconnection)?;
+                    }
+                    let error = match exchange(connection, new_game, configuration, clock) {
+                        Ok(movement) => return Ok(movement),
+                        Err(e) => e,
+                    };
+                    let reason = read_failure(error.kind());
+                    let resumable = reason == ForfeitReason::Disconnection
+                        && error.kind() != io::ErrorKind::InvalidData;
+                    let session = match &self.session {
+                        Some((session, grace)) if resumable => (session, *grace),
+                        _ => return Err(reason),
+                    };
+                    // the clock keeps running while waiting for the client
+                    let grace = match clock {
+                        Some(clock) => session.1.min(clock.saturating_sub(start.elapsed())),
+                        None => session.1,
+                    };
+                    println!("{} lost its connection, waiting {:?}", self.name, grace);
+                    match session.0.wait(grace) {
+                        Some(reconnected) => {
+                            println!("{} reconnected", self.name);
+                            *connection = reconnected;
+                            let clock = self
+                                .timeout
+                                .get()
+                                .map(|t| t.saturating_sub(start.elapsed()));
+                            if clock == Some(Duration::default()) {
+                                return Err(ForfeitReason::Timeout);
+                            }
+                            connection
+                                .stream()
+                                .set_read_timeout(clock)
+                                .map_err(|_| ForfeitReason::Disconnection)?;
+                        }
+                        None if clock.is_some() && grace < session.1 => {
+                            return Err(ForfeitReason::Timeout)
+                        }
+                        None => return Err(reason),
+                    }
+                }
+            }
         }
     }
 }
 
+/// Send the position (after the game's start if needed) and wait for the client's move.
+fn exchange(
+    connection: &mut Connection,
+    start: &mut Option<Fen>,
+    configuration: &Configuration,
+    clock: Option<Duration>,
+) -> io::Result<Option<Movement>> {
+    if let Some(position) = *start {
+        connection.send(&Message::NewGame {
+            board: position.to_string(),
+            color: Color::from_player(configuration.current_player),
+        })?;
+        *start = None;
+    }
+    connection.send(&Message::Position {
+        position: Fen::from_configuration(configuration, 0).to_string(),
+        clock: clock.map(|c| c.as_millis() as u64),
+    })?;
+    match connection.receive()? {
+        Message::Move { movement } => Ok(movement),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected move, got {:?}", other),
+        )),
+    }
+}
+
 impl Strategy for NetworkPlayer {
     fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
-        let mut message = configuration.serialize();
-        message.push('\n');
-        self.connection
-            .write_all(message.into_bytes().as_slice())
-            .expect("sending configuration remotely failed");
-        self.movements.next().unwrap().unwrap()
+        let result = self
+            .receive_move(configuration)
+            .and_then(|movement| match movement {
+                Some(m) if !configuration.check_move(&m) => Err(ForfeitReason::IllegalMove),
+                // passing is only allowed when stuck
+                None if configuration.movements().next().is_some() => {
+                    Err(ForfeitReason::IllegalMove)
+                }
+                movement => Ok(movement),
+            });
+        match result {
+            Ok(movement) => movement,
+            Err(reason) => {
+                if let Link::Messages { connection, .. } = &mut self.link {
+                    let _ = connection.send(&Message::Error {
+                        message: format!("you forfeit the game ({})", reason),
+                    });
+                }
+                self.request = Some(Request::Forfeit(reason));
+                None
+            }
+        }
+    }
+
+    fn request(&mut self) -> Option<Request> {
+        self.request.take()
+    }
+
+    fn game_started(&mut self, configuration: &Configuration) {
+        if let Link::Messages { start, .. } = &mut self.link {
+            *start = Some(Fen::from_configuration(configuration, 0));
+        }
+    }
+
+    fn game_over(&mut self, end: &Configuration, termination: Option<&Termination>) {
+        if let Link::Messages { connection, start } = &mut self.link {
+            *start = None;
+            let _ = connection.send(&Message::GameOver {
+                result: end.red_value(),
+                termination: termination.map(|t| t.to_string()),
+                position: Some(Fen::from_configuration(end, 0).to_string()),
+            });
+        }
     }
 }
@@ -0,0 +1,238 @@
+//! External engines speaking the text engine protocol (see `engine`).
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Request, Strategy};
+use crate::configuration::{Configuration, Movement};
+use crate::engine::{self, Limit, Reply};
+use crate::notation::Fen;
+use crate::record::ForfeitReason;
+
+/// How long engines have to answer besides searching.
+const MARGIN: Duration = Duration::from_secs(1);
+
+/// How long engines have to start.
+const STARTUP: Duration = Duration::from_secs(10);
+
+/// How long engines have to answer by default when they do not search for a given time.
+const MOVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Engine run as a child process (talking on its stdin and stdout) or reached on a Unix-domain
+/// socket.
+///
+/// Like remote players, engines crashing, overstepping their time or playing illegal moves
+/// forfeit the game.
+pub struct ProcessEngine {
+    name: String,
+    child: Option<Child>,
+    commands: Box<dyn Write + Send>,
+    replies: Receiver<Reply>,
+    limit: Option<Limit>,
+    timeout: Option<Duration>,
+    request: Option<Request>,
+    /// Searches we gave up on : their `bestmove` is still to come.
+    abandoned: usize,
+}
+
+impl fmt::Display for ProcessEngine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "External engine : {}", self.name)
+    }
+}
+
+impl ProcessEngine {
+    /// Start given command (program and arguments separated by spaces) and say `blobwar`.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let commands = child.stdin.take().expect("piped stdin");
+        let replies = BufReader::new(child.stdout.take().expect("piped stdout"));
+        ProcessEngine::start(Some(child), replies, commands)
+    }
+
+    /// Connect to an engine listening on given Unix-domain socket and say `blobwar`.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        let replies = BufReader::new(stream.try_clone()?);
+        ProcessEngine::start(None, replies, stream)
+    }
+
+    fn start<R, W>(child: Option<Child>, replies: R, commands: W) -> io::Result<Self>
+    where
+        R: BufRead + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            // engines may chat, only replies matter
+            for reply in replies.lines().map_while(Result::ok) {
+                if let Ok(reply) = reply.parse() {
+                    if sender.send(reply).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        let mut engine = ProcessEngine {
+            name: "unknown".to_owned(),
+            child,
+            commands: Box::new(commands),
+            replies: receiver,
+            limit: None,
+            timeout: Some(MOVE_TIMEOUT),
+            request: None,
+            abandoned: 0,
+        };
+        engine.send(&engine::Command::Blobwar)?;
+        let deadline = Instant::now() + STARTUP;
+        loop {
+            match engine.receive(Some(deadline))? {
+                Reply::Id(name) => engine.name = name,
+                Reply::BlobwarOk => return Ok(engine),
+                _ => (),
+            }
+        }
+    }
+
+    /// Search with given limit (the engine's own if none).
+    pub fn limit(mut self, limit: Option<Limit>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Forfeit if the engine takes longer than `timeout` to answer (`None` to wait forever),
+    /// one minute by default. Engines searching for a given time get this time instead.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Change one of the engine's options, waiting until it is done.
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&engine::Command::SetOption {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        })?;
+        self.send(&engine::Command::IsReady)?;
+        let deadline = Instant::now() + STARTUP;
+        while self.receive(Some(deadline))? != Reply::ReadyOk {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &engine::Command) -> io::Result<()> {
+        writeln!(self.commands, "{}", command)?;
+        self.commands.flush()
+    }
+
+    /// Next reply, failing with `TimedOut` past the deadline if any.
+    fn receive(&self, deadline: Option<Instant>) -> io::Result<Reply> {
+        let exited = || io::Error::new(io::ErrorKind::UnexpectedEof, "engine exited");
+        match deadline {
+            Some(deadline) => self
+                .replies
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map_err(|e| match e {
+                    RecvTimeoutError::Timeout => {
+                        io::Error::new(io::ErrorKind::TimedOut, "engine did not answer")
+                    }
+                    RecvTimeoutError::Disconnected => exited(),
+                }),
+            None => self.replies.recv().map_err(|_| exited()),
+        }
+    }
+
+    /// Ask for a move, without validating it.
+    fn search(&mut self, configuration: &Configuration) -> Result<Option<Movement>, ForfeitReason> {
+        let disconnected = |_| ForfeitReason::Disconnection;
+        let mut deadline = match self.limit {
+            Some(Limit::MoveTime(time)) => Some(Instant::now() + Duration::from_millis(time)),
+            _ => self.timeout.map(|timeout| Instant::now() + timeout),
+        };
+        // late answers would be taken for this search's, they use up its time
+        while self.abandoned > 0 {
+            match self.receive(deadline.map(|deadline| deadline + MARGIN)) {
+                Ok(Reply::BestMove(_)) => self.abandoned -= 1,
+                Ok(_) => (),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    return Err(ForfeitReason::Timeout)
+                }
+                Err(_) => return Err(ForfeitReason::Disconnection),
+            }
+        }
+        self.send(&engine::Command::Position(Fen::from_configuration(
+            configuration,
+            0,
+        )))
+        .map_err(disconnected)?;
+        self.send(&engine::Command::Go(self.limit))
+            .map_err(disconnected)?;
+        let mut stopped = false;
+        loop {
+            match self.receive(deadline.map(|deadline| deadline + MARGIN)) {
+                Ok(Reply::BestMove(movement)) => return Ok(movement),
+                Ok(_) => (),
+                // one last chance
+                Err(e) if e.kind() == io::ErrorKind::TimedOut && !stopped => {
+                    self.send(&engine::Command::Stop).map_err(disconnected)?;
+                    stopped = true;
+                    deadline = Some(Instant::now());
+                }
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    self.abandoned += 1;
+                    return Err(ForfeitReason::Timeout);
+                }
+                Err(_) => return Err(ForfeitReason::Disconnection),
+            }
+        }
+    }
+}
+
+impl Strategy for ProcessEngine {
+    fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
+        let result = self
+            .search(configuration)
+            .and_then(|movement| match movement {
+                Some(m) if !configuration.check_move(&m) => Err(ForfeitReason::IllegalMove),
+                movement => Ok(movement),
+            });
+        result.unwrap_or_else(|reason| {
+            eprintln!("{} forfeits ({})", self.name, reason);
+            self.request = Some(Request::Forfeit(reason));
+            None
+        })
+    }
+
+    fn request(&mut self) -> Option<Request> {
+        self.request.take()
+    }
+}
+
+impl Drop for ProcessEngine {
+    fn drop(&mut self) {
+        let _ = self.send(&engine::Command::Quit);
+        if let Some(child) = &mut self.child {
+            let deadline = Instant::now() + MARGIN;
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
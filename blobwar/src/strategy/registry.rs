@@ -0,0 +1,271 @@
+//! Registry of all engines buildable from an `EngineSpec`.
+use std::fmt;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use super::{
+    alpha_beta_anytime, alpha_beta_pass_anytime, alpha_beta_table_anytime, min_max_anytime,
+    AlphaBeta, AlphaBetaPass, AlphaBetaTable, BenchmarkUnitaire, EngineSpec, Greedy, Human,
+    IterativeDeepening, MinMax, NetworkPlayer, Strategy,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use super::{min_max_par_anytime, MinMaxPar, ProcessEngine, Tui};
+use crate::configuration::Configuration;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::engine::Limit;
+
+/// Depth used by search engines when neither depth nor time is given.
+pub const DEFAULT_DEPTH: u8 = 4;
+
+/// Default port on which network players are awaited.
+pub const DEFAULT_PORT: u16 = 12_345;
+
+/// Engines playing on their own, the only ones browsers and engine controllers may ask for : the
+/// others read the terminal, listen on the network or run commands.
+pub const SEARCH_ENGINES: [&str; 6] = [
+    "greedy",
+    "minmax",
+    "minmaxpar",
+    "alphabeta",
+    "alphabetapass",
+    "alphabetatable",
+];
+
+/// Build a `Strategy` out of a spec.
+pub type Factory = Box<dyn Fn(&EngineSpec) -> Result<Box<dyn Strategy>, String> + Send + Sync>;
+
+/// Anytime search, storing better and better moves in the shared memory segment of given name
+/// until killed. Run by `blobwar_iterative_deepening` on behalf of `IterativeDeepening`.
+pub type Anytime = fn(&Configuration, &str);
+
+struct Entry {
+    name: &'static str,
+    description: &'static str,
+    factory: Factory,
+    anytime: Option<Anytime>,
+}
+
+/// Associate engine names to factories.
+/// The default registry contains all engines of this crate.
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Registry {
+    /// Registry without any engine.
+    pub fn empty() -> Self {
+        Registry {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add (or replace) an engine.
+    pub fn register(&mut self, name: &'static str, description: &'static str, factory: Factory) {
+        self.insert(Entry {
+            name,
+            description,
+            factory,
+            anytime: None,
+        });
+    }
+
+    /// Add (or replace) a search engine running at fixed depth with a `depth` option
+    /// and through iterative deepening with the given anytime search with a `time` option.
+    pub fn register_searching<S: BenchmarkUnitaire + 'static>(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        anytime: Anytime,
+    ) {
+        self.insert(Entry {
+            name,
+            description,
+            factory: searching::<S>(name),
+            anytime: Some(anytime),
+        });
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        self.entries.retain(|e| e.name != entry.name);
+        self.entries.push(entry);
+    }
+
+    /// Iterate on all engine names with their description.
+    pub fn engines(&self) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        self.entries.iter().map(|e| (e.name, e.description))
+    }
+
+    /// Build the `Strategy` described by given spec.
+    pub fn build(&self, spec: &EngineSpec) -> Result<Box<dyn Strategy>, String> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.name == spec.strategy)
+            .ok_or_else(|| format!("unknown strategy {}", spec.strategy))?;
+        (entry.factory)(spec)
+    }
+
+    /// Anytime search of given engine if it has one.
+    pub fn anytime(&self, name: &str) -> Option<Anytime> {
+        self.entries
+            .iter()
+            .find(|e| e.name == name)
+            .and_then(|e| e.anytime)
+    }
+
+    /// Parse and build given spec string.
+    pub fn parse(&self, spec: &str) -> Result<Box<dyn Strategy>, String> {
+        self.build(&spec.parse()?)
+    }
+}
+
+/// Factory for engines without any parameter.
+fn simple<S: Strategy + 'static>(make: fn() -> S) -> Factory {
+    Box::new(move |spec| {
+        if spec.depth.is_some() || spec.time.is_some() {
+            return Err(format!("{} takes no depth nor time", spec.strategy));
+        }
+        Ok(Box::new(make()))
+    })
+}
+
+/// Factory for search engines: fixed depth or iterative deepening if a time is given.
+fn searching<S: BenchmarkUnitaire + 'static>(name: &'static str) -> Factory {
+    Box::new(move |spec| {
+        Ok(match (spec.depth, spec.time) {
+            (Some(_), Some(_)) => return Err("depth and time are exclusive".to_owned()),
+            // iterative deepening needs processes and shared memory
+            (_, Some(_)) if cfg!(target_arch = "wasm32") => {
+                return Err("no time control on wasm, give a depth".to_owned())
+            }
+            (_, Some(duration)) => Box::new(IterativeDeepening::new(name).duration(duration)),
+            (depth, None) => Box::new(S::new(depth.unwrap_or(DEFAULT_DEPTH))),
+        })
+    })
+}
+
+/// Human player, getting hints from the `hint` engine spec.
+fn human(spec: &EngineSpec) -> Result<Box<dyn Strategy>, String> {
+    if spec.depth.is_some() || spec.time.is_some() {
+        return Err("human takes no depth nor time".to_owned());
+    }
+    Ok(Box::new(match spec.option("hint")? {
+        Some(hint) => Human::with_hint(hint),
+        None => Human::default(),
+    }))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Full-screen human player, evaluating positions with the `eval` engine spec.
+fn tui(spec: &EngineSpec) -> Result<Box<dyn Strategy>, String> {
+    if spec.depth.is_some() || spec.time.is_some() {
+        return Err("tui takes no depth nor time".to_owned());
+    }
+    Ok(Box::new(match spec.option("eval")? {
+        Some(eval) => Tui::with_evaluator_spec(&eval)?,
+        None => Tui::default(),
+    }))
+}
+
+/// Wait for a remote client on the `port` option, forfeiting after `timeout` milliseconds.
+fn network(spec: &EngineSpec) -> Result<Box<dyn Strategy>, String> {
+    let port = spec.option("port")?.unwrap_or(DEFAULT_PORT);
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| e.to_string())?;
+    println!("waiting for network player on port {}", port);
+    let (stream, _) = listener.accept().map_err(|e| e.to_string())?;
+    let player = if spec.option("legacy")?.unwrap_or(false) {
+        NetworkPlayer::legacy(stream)
+    } else {
+        NetworkPlayer::new(stream)
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(Box::new(match spec.option("timeout")? {
+        Some(timeout) => player
+            .timeout(Duration::from_millis(timeout))
+            .map_err(|e| e.to_string())?,
+        None => player,
+    }))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// External engine started with the `command` option or reached on the `socket` option.
+/// `depth` and `time` limit its searches, it forfeits after `timeout` milliseconds without
+/// answering (when not searching for a given time), other options are given to the engine.
+fn process(spec: &EngineSpec) -> Result<Box<dyn Strategy>, String> {
+    let mut engine = match (spec.options.get("command"), spec.options.get("socket")) {
+        (Some(command), None) => ProcessEngine::spawn(command),
+        (None, Some(socket)) => ProcessEngine::connect(socket),
+        _ => return Err("process needs either a command or a socket".to_owned()),
+    }
+    .map_err(|e| format!("failed starting engine: {}", e))?;
+    for (name, value) in &spec.options {
+        if name != "command" && name != "socket" && name != "timeout" {
+            engine.set_option(name, value).map_err(|e| e.to_string())?;
+        }
+    }
+    let limit = match (spec.depth, spec.time) {
+        (Some(_), Some(_)) => return Err("depth and time are exclusive".to_owned()),
+        (Some(depth), None) => Some(Limit::Depth(depth)),
+        (None, Some(time)) => Some(Limit::MoveTime(time)),
+        (None, None) => None,
+    };
+    let engine = engine.limit(limit);
+    Ok(Box::new(match spec.option("timeout")? {
+        Some(timeout) => engine.timeout(Some(Duration::from_millis(timeout))),
+        None => engine,
+    }))
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register("human", "moves entered on stdin (hint)", Box::new(human));
+        #[cfg(not(target_arch = "wasm32"))]
+        registry.register("tui", "full-screen terminal player (eval)", Box::new(tui));
+        registry.register("greedy", "best immediate value", simple(Greedy));
+        registry.register_searching::<MinMax>("minmax", "min-max (depth or time)", min_max_anytime);
+        // no threads on wasm
+        #[cfg(not(target_arch = "wasm32"))]
+        registry.register_searching::<MinMaxPar>(
+            "minmaxpar",
+            "parallel min-max (depth or time)",
+            min_max_par_anytime,
+        );
+        registry.register_searching::<AlphaBeta>(
+            "alphabeta",
+            "alpha-beta (depth or time)",
+            alpha_beta_anytime,
+        );
+        registry.register_searching::<AlphaBetaPass>(
+            "alphabetapass",
+            "alpha-beta with pass heuristic (depth or time)",
+            alpha_beta_pass_anytime,
+        );
+        registry.register_searching::<AlphaBetaTable>(
+            "alphabetatable",
+            "alpha-beta with transposition table (depth or time)",
+            alpha_beta_table_anytime,
+        );
+        registry.register(
+            "network",
+            "remote client (port, legacy, timeout in ms)",
+            Box::new(network),
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        registry.register(
+            "process",
+            "external engine on stdin/stdout (command) or unix socket (socket, depth, time)",
+            Box::new(process),
+        );
+        registry
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, description) in self.engines() {
+            writeln!(f, "{:<16}{}", name, description)?;
+        }
+        Ok(())
+    }
+}
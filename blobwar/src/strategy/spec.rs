@@ -0,0 +1,114 @@
+//! Textual engine specifications.
+//!
+//! A spec is a strategy name optionally followed by `:` and comma separated options, for example
+//! `alphabeta:depth=4`, `alphabetapass:time=500` or `minmax:depth=3,name=mm3`.
+//! `depth` selects a fixed depth search, `time` an iterative deepening search in milliseconds.
+//! Any other option is kept for the engine factory (like `network:port=12345`).
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::registry::Registry;
+use super::Strategy;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Parsed engine specification.
+pub struct EngineSpec {
+    /// Strategy name (`minmax`, `alphabeta`, ...).
+    pub strategy: String,
+    /// Fixed search depth.
+    pub depth: Option<u8>,
+    /// Time per move in milliseconds (iterative deepening).
+    pub time: Option<u64>,
+    /// Optional label used in results tables.
+    pub name: Option<String>,
+    /// Engine specific options.
+    pub options: BTreeMap<String, String>,
+}
+
+impl EngineSpec {
+    /// Label of this engine: its name if given, else its canonical spec.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            let mut spec = self.clone();
+            spec.name = None;
+            spec.to_string()
+        })
+    }
+
+    /// Engine specific option parsed into the requested type.
+    pub fn option<T: FromStr>(&self, key: &str) -> Result<Option<T>, String> {
+        self.options
+            .get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value {} for option {}", value, key))
+            })
+            .transpose()
+    }
+
+    /// Build the corresponding `Strategy` with the default `Registry`.
+    pub fn build(&self) -> Result<Box<dyn Strategy>, String> {
+        Registry::default().build(self)
+    }
+}
+
+impl FromStr for EngineSpec {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut parts = string.splitn(2, ':');
+        let strategy = parts.next().unwrap_or_default().trim().to_lowercase();
+        if strategy.is_empty() {
+            return Err("empty engine spec".to_owned());
+        }
+        let mut spec = EngineSpec {
+            strategy,
+            ..Default::default()
+        };
+        for option in parts.next().into_iter().flat_map(|o| o.split(',')) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("option {} is not of the form key=value", option))?;
+            match key.trim() {
+                "depth" => {
+                    spec.depth = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid depth {}", value))?,
+                    )
+                }
+                "time" => {
+                    spec.time = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid time {}", value))?,
+                    )
+                }
+                "name" => spec.name = Some(value.to_owned()),
+                other => {
+                    spec.options.insert(other.to_owned(), value.to_owned());
+                }
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for EngineSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.strategy)?;
+        let options = self
+            .depth
+            .map(|d| format!("depth={}", d))
+            .into_iter()
+            .chain(self.time.map(|t| format!("time={}", t)))
+            .chain(self.options.iter().map(|(k, v)| format!("{}={}", k, v)))
+            .chain(self.name.as_ref().map(|n| format!("name={}", n)))
+            .collect::<Vec<_>>();
+        if !options.is_empty() {
+            write!(f, ":{}", options.join(","))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,484 @@
+//! Full-screen terminal player.
+//!
+//! When it is our turn the terminal switches to a full-screen view : move a cursor with the
+//! arrow keys, select one of our blobs with enter or space (possible destinations are shown,
+//! green for duplications and yellow for jumps) and select a destination to play.
+//! A side panel shows the evaluation of an analysis engine, the clocks and the moves played.
+//! The evaluation runs in the background and the panel follows its progress, it stops once we
+//! played.
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{EngineSpec, Request, Strategy};
+use crate::analysis::{Analysis, Analyzer};
+use crate::configuration::{format_move, Configuration, Movement};
+use crate::notation::Fen;
+use crate::positions::{BoardPosition, Position};
+
+const HELP: &str = "arrows: move  enter: select/play  c: cancel  h: hint  u: undo  r: resign";
+
+/// Column of the side panel.
+const PANEL: usize = 24;
+
+/// Number of moves shown in the side panel.
+const SHOWN_MOVES: usize = 12;
+
+/// Put the terminal in raw mode on the alternate screen until dropped.
+struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    fn new() -> io::Result<Self> {
+        let mut termios: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = termios;
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+        // reads give up after a tenth of a second so that we can follow the evaluation
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 1;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(RawTerminal { original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Keys we understand.
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Char(char),
+}
+
+/// Next byte of given input, `None` if nothing came for a while.
+fn read_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    Ok(match input.read(&mut byte)? {
+        0 => None,
+        _ => Some(byte[0]),
+    })
+}
+
+/// Next key typed on given input, `None` if nothing came for a while.
+fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    loop {
+        return Ok(Some(match read_byte(input)? {
+            None => return Ok(None),
+            Some(b'\r') | Some(b'\n') | Some(b' ') => Key::Enter,
+            Some(0x1b) => {
+                if read_byte(input)? != Some(b'[') {
+                    continue;
+                }
+                match read_byte(input)? {
+                    Some(b'A') => Key::Up,
+                    Some(b'B') => Key::Down,
+                    Some(b'C') => Key::Right,
+                    Some(b'D') => Key::Left,
+                    _ => continue,
+                }
+            }
+            Some(byte) => Key::Char(byte as char),
+        }));
+    }
+}
+
+/// Analysis running in another thread, stopped when dropped.
+struct Evaluation {
+    /// Analysis of every completed depth.
+    analyses: Receiver<Analysis>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Evaluation {
+    /// Analyze given position until the analyzer's depth or time limit.
+    fn start(analyzer: Analyzer, position: Fen) -> Self {
+        let (sender, analyses) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let analyzer = analyzer.stop_flag(stop.clone());
+        thread::spawn(move || {
+            let board = position.board();
+            let _ = analyzer.analyze_with(&position.configuration(&board), |analysis| {
+                let _ = sender.send(analysis.clone());
+            });
+        });
+        Evaluation { analyses, stop }
+    }
+}
+
+impl Drop for Evaluation {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Human player through a full-screen terminal interface.
+pub struct Tui {
+    evaluator: Option<Analyzer>,
+    hint: Option<Movement>,
+    request: Option<Request>,
+    cursor: Position,
+    /// Time spent by red and blue.
+    clocks: [Duration; 2],
+    /// When we last returned a move.
+    last_return: Option<Instant>,
+    /// Position after our last move (to deduce what the adversary played).
+    after_our_move: Option<Fen>,
+    /// Positions before each move with the move played.
+    moves: Vec<(Fen, Option<Movement>)>,
+}
+
+impl std::fmt::Display for Tui {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Human (terminal)")
+    }
+}
+
+impl Default for Tui {
+    fn default() -> Self {
+        Tui::with_evaluator(Analyzer::from_spec(&"alphabeta:time=300".parse().unwrap()).ok())
+    }
+}
+
+impl Tui {
+    /// Terminal player displaying the evaluation of given analyzer (if any).
+    pub fn with_evaluator(evaluator: Option<Analyzer>) -> Self {
+        Tui {
+            evaluator,
+            hint: None,
+            request: None,
+            cursor: 0,
+            clocks: Default::default(),
+            last_return: None,
+            after_our_move: None,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Terminal player evaluating positions with given engine spec.
+    pub fn with_evaluator_spec(spec: &EngineSpec) -> Result<Self, String> {
+        Ok(Tui::with_evaluator(Some(Analyzer::from_spec(spec)?)))
+    }
+
+    /// Update move list and clocks with what happened since our last move.
+    fn catch_up(&mut self, configuration: &Configuration) {
+        let now = Fen::from_configuration(configuration, 0);
+        if let Some(instant) = self.last_return.take() {
+            self.clocks[!configuration.current_player as usize] += instant.elapsed();
+        }
+        // after an undo we are back to an older position
+        if let Some(index) = self.moves.iter().position(|(before, _)| *before == now) {
+            self.moves.truncate(index);
+            self.after_our_move = None;
+            return;
+        }
+        if let Some(before) = self.after_our_move.take() {
+            let board = before.board();
+            let previous = before.configuration(&board);
+            let adversary_move = if Fen::from_configuration(&previous.skip_play(), 0) == now {
+                Some(None)
+            } else {
+                previous
+                    .movements()
+                    .find(|m| Fen::from_configuration(&previous.play(m), 0) == now)
+                    .map(Some)
+            };
+            if let Some(movement) = adversary_move {
+                self.moves.push((before, movement));
+            }
+        }
+    }
+
+    /// Record our move before returning it.
+    fn played(&mut self, configuration: &Configuration, movement: Option<Movement>) {
+        self.moves
+            .push((Fen::from_configuration(configuration, 0), movement));
+        let after = movement.map_or_else(|| configuration.skip_play(), |m| configuration.play(&m));
+        self.after_our_move = Some(Fen::from_configuration(&after, 0));
+        self.last_return = Some(Instant::now());
+    }
+
+    fn draw(
+        &self,
+        configuration: &Configuration,
+        selected: Option<Position>,
+        evaluation: &Option<Analysis>,
+        message: &str,
+    ) -> String {
+        let cells: Vec<char> = configuration.serialize().chars().skip(1).collect();
+        let board = configuration.board();
+        let destinations = |distance: usize| -> Vec<Position> {
+            selected.map_or_else(Vec::new, |source| {
+                board.individual_neighbours[distance][source as usize]
+                    .iter()
+                    .cloned()
+                    .filter(|&p| configuration.free_position_at(p))
+                    .collect()
+            })
+        };
+        let (near, far) = (destinations(0), destinations(1));
+
+        let mut lines = vec![String::new(); 12];
+        lines[0] = "   a b c d e f g h".to_owned();
+        for y in 0..8u8 {
+            let line = &mut lines[y as usize + 1];
+            write!(line, "{:>2} ", y + 1).unwrap();
+            for x in 0..8u8 {
+                let position = Position::from_2d(x, y);
+                let mut style = String::new();
+                if near.contains(&position) {
+                    style.push_str("\x1b[42m");
+                } else if far.contains(&position) {
+                    style.push_str("\x1b[43m");
+                }
+                if Some(position) == selected {
+                    style.push_str("\x1b[1;4m");
+                }
+                if position == self.cursor {
+                    style.push_str("\x1b[7m");
+                }
+                let cell = match cells[position as usize] {
+                    'h' => "\x1b[90m#",
+                    'r' => "\x1b[31mx",
+                    'b' => "\x1b[36mo",
+                    _ => ".",
+                };
+                write!(line, "{}{}\x1b[0m ", style, cell).unwrap();
+            }
+        }
+
+        let (red, blue) = (
+            cells.iter().filter(|&&c| c == 'r').count(),
+            cells.iter().filter(|&&c| c == 'b').count(),
+        );
+        let me = ["red", "blue"][configuration.current_player as usize];
+        let mut panel = vec![
+            format!("you play {}", me),
+            format!("blobs: red {} blue {}", red, blue),
+            format!(
+                "clocks: red {} blue {}",
+                format_clock(self.clocks[0]),
+                format_clock(self.clocks[1])
+            ),
+        ];
+        panel.push(
+            match evaluation.as_ref().and_then(|e| e.best().map(|b| (e, b))) {
+                Some((analysis, best)) => format!(
+                    "eval {:+} depth {} best {}",
+                    best.score,
+                    analysis.depth,
+                    format_move(&best.movement)
+                ),
+                None => "eval -".to_owned(),
+            },
+        );
+        if let Some(hint) = self.hint {
+            panel.push(format!("hint: {}", hint));
+        }
+        panel.push("moves:".to_owned());
+        let first_shown = self.moves.len().saturating_sub(SHOWN_MOVES);
+        for (ply, (_, movement)) in self.moves.iter().enumerate().skip(first_shown) {
+            panel.push(format!("{:>3}. {}", ply + 1, format_move(movement)));
+        }
+
+        let mut screen = String::from("\x1b[2J\x1b[H");
+        for row in 0..lines.len().max(panel.len()) {
+            let left = lines.get(row).map_or("", |l| l.as_str());
+            let right = panel.get(row).map_or("", |l| l.as_str());
+            write!(
+                screen,
+                "\x1b[{};1H{}\x1b[{};{}H{}",
+                row + 1,
+                left,
+                row + 1,
+                PANEL,
+                right
+            )
+            .unwrap();
+        }
+        let bottom = lines.len().max(panel.len()) + 2;
+        write!(
+            screen,
+            "\x1b[{};1H{}\x1b[{};1H{}",
+            bottom,
+            HELP,
+            bottom + 1,
+            message
+        )
+        .unwrap();
+        screen
+    }
+
+    /// Interact until the user plays or asks for something, following the evaluation.
+    fn interact(
+        &mut self,
+        configuration: &Configuration,
+        evaluation: Option<Evaluation>,
+    ) -> io::Result<Option<Movement>> {
+        let _terminal = RawTerminal::new()?;
+        let mut selected: Option<Position> = None;
+        let mut message = String::new();
+        let mut analysis = None;
+        let mut redraw = true;
+        let player_blobs = |position: Position| {
+            configuration.serialize().chars().nth(position as usize + 1)
+                == Some(if configuration.current_player {
+                    'b'
+                } else {
+                    'r'
+                })
+        };
+        loop {
+            if let Some(latest) = evaluation
+                .as_ref()
+                .and_then(|e| e.analyses.try_iter().last())
+            {
+                analysis = Some(latest);
+                redraw = true;
+            }
+            if redraw {
+                print!(
+                    "{}",
+                    self.draw(configuration, selected, &analysis, &message)
+                );
+                io::stdout().flush()?;
+                message.clear();
+                redraw = false;
+            }
+            let key = match read_key(&mut io::stdin())? {
+                Some(key) => key,
+                None => continue,
+            };
+            redraw = true;
+            let (x, y) = self.cursor.to_2d();
+            match key {
+                Key::Up => self.cursor = Position::from_2d(x, y.saturating_sub(1)),
+                Key::Down => self.cursor = Position::from_2d(x, (y + 1).min(7)),
+                Key::Left => self.cursor = Position::from_2d(x.saturating_sub(1), y),
+                Key::Right => self.cursor = Position::from_2d((x + 1).min(7), y),
+                Key::Char('c') => selected = None,
+                Key::Char('u') => {
+                    self.request = Some(Request::Undo);
+                    return Ok(None);
+                }
+                Key::Char('r') | Key::Char('q') => {
+                    self.request = Some(Request::Resign);
+                    return Ok(None);
+                }
+                Key::Char('h') => {
+                    self.hint = analysis
+                        .as_ref()
+                        .and_then(|e| e.best())
+                        .and_then(|b| b.movement);
+                    if self.hint.is_none() {
+                        message = "no hint available".to_owned();
+                    }
+                }
+                Key::Char(_) => message = HELP.to_owned(),
+                Key::Enter => match selected {
+                    _ if player_blobs(self.cursor) => selected = Some(self.cursor),
+                    None => message = "select one of your blobs first".to_owned(),
+                    Some(source) => {
+                        let movement = match source.distance_to(self.cursor) {
+                            1 => Movement::Duplicate(self.cursor),
+                            _ => Movement::Jump(source, self.cursor),
+                        };
+                        if configuration.check_move(&movement) {
+                            return Ok(Some(movement));
+                        }
+                        message = format!("{} is not a legal move", movement);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Strategy for Tui {
+    fn compute_next_move(&mut self, configuration: &Configuration) -> Option<Movement> {
+        let start = Instant::now();
+        self.catch_up(configuration);
+        self.hint = None;
+        let movement = if configuration.movements().next().is_none() {
+            None
+        } else {
+            let evaluation = self
+                .evaluator
+                .clone()
+                .map(|e| Evaluation::start(e, Fen::from_configuration(configuration, 0)));
+            match self.interact(configuration, evaluation) {
+                Ok(movement) => movement,
+                Err(e) => {
+                    println!("terminal error ({}), resigning", e);
+                    self.request = Some(Request::Resign);
+                    None
+                }
+            }
+        };
+        self.clocks[configuration.current_player as usize] += start.elapsed();
+        if self.request.is_none() {
+            self.played(configuration, movement);
+        }
+        movement
+    }
+
+    fn request(&mut self) -> Option<Request> {
+        self.request.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(mut input: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut input).unwrap() {
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn keys_are_parsed() {
+        assert_eq!(
+            keys(b"\x1b[A\x1b[B\x1b[C\x1b[D"),
+            vec![Key::Up, Key::Down, Key::Right, Key::Left]
+        );
+        assert_eq!(
+            keys(b"\r\n u"),
+            vec![Key::Enter, Key::Enter, Key::Enter, Key::Char('u')]
+        );
+        // unknown escape sequences are skipped
+        assert_eq!(keys(b"\x1bOP\x1b[Zh"), vec![Key::Char('P'), Key::Char('h')]);
+        assert_eq!(keys(b""), vec![]);
+    }
+}
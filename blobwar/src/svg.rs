@@ -0,0 +1,203 @@
+//! SVG diagrams of configurations, for reports and game records.
+//!
+//! A diagram shows holes, blobs, coordinates, an arrow for the last jump (a ring for the last
+//! duplication) and highlights the cells flipped by the last move. A whole game can also be
+//! drawn as one animated SVG, showing each ply in turn.
+use std::fmt::Write;
+
+use crate::configuration::Configuration;
+use crate::positions::{BoardPosition, Position};
+use crate::render::Markers;
+
+/// Size of a cell in pixels.
+pub(crate) const CELL: u32 = 40;
+
+/// Space left for coordinates.
+pub(crate) const MARGIN: u32 = 24;
+
+/// Width and height of the whole picture.
+pub(crate) const SIZE: u32 = 2 * MARGIN + 8 * CELL;
+
+const RED: &str = "#d62728";
+const BLUE: &str = "#1f77b4";
+
+/// Center of given cell in the picture.
+pub(crate) fn center(position: Position) -> (u32, u32) {
+    let (x, y) = position.to_2d();
+    (
+        MARGIN + x as u32 * CELL + CELL / 2,
+        MARGIN + y as u32 * CELL + CELL / 2,
+    )
+}
+
+fn header(svg: &mut String) {
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}" font-family="sans-serif" font-size="14">
+<defs><marker id="arrow" markerWidth="8" markerHeight="8" refX="6" refY="4" orient="auto"><path d="M0,0 L8,4 L0,8 z" fill="black"/></marker></defs>
+<rect width="{0}" height="{0}" fill="white"/>"#,
+        SIZE
+    )
+    .unwrap();
+    for index in 0..8u32 {
+        let offset = MARGIN + index * CELL + CELL / 2;
+        let column = (b'a' + index as u8) as char;
+        writeln!(
+            svg,
+            r#"<text x="{0}" y="{1}" text-anchor="middle">{2}</text><text x="{1}" y="{3}" text-anchor="middle" dominant-baseline="middle">{4}</text>"#,
+            offset,
+            MARGIN - 8,
+            column,
+            offset,
+            index + 1
+        )
+        .unwrap();
+    }
+}
+
+/// Cells, blobs and markers of one configuration.
+fn board(svg: &mut String, configuration: &Configuration, markers: &Markers) {
+    let holes = configuration.board().holes;
+    let blobs = configuration.blobs();
+    for position in 0..64 {
+        let (x, y) = center(position);
+        let (left, top) = (x - CELL / 2, y - CELL / 2);
+        let fill = if holes.contains(position) {
+            "#404040"
+        } else if markers.flipped.contains(position) {
+            "#ffe680"
+        } else {
+            "#e8e8e8"
+        };
+        writeln!(
+            svg,
+            r#"<rect x="{0}" y="{1}" width="{2}" height="{2}" fill="{3}" stroke="white"/>"#,
+            left, top, CELL, fill
+        )
+        .unwrap();
+        let color = if blobs[0].contains(position) {
+            RED
+        } else if blobs[1].contains(position) {
+            BLUE
+        } else {
+            continue;
+        };
+        writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            x,
+            y,
+            CELL * 2 / 5,
+            color
+        )
+        .unwrap();
+    }
+    if let Some(destination) = markers.destination {
+        let (x, y) = center(destination);
+        match markers.source {
+            Some(source) => {
+                let (from_x, from_y) = center(source);
+                writeln!(
+                    svg,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" stroke-width="3" marker-end="url(#arrow)"/>"#,
+                    from_x, from_y, x, y
+                )
+                .unwrap();
+            }
+            None => writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="black" stroke-width="3"/>"#,
+                x,
+                y,
+                CELL * 2 / 5
+            )
+            .unwrap(),
+        }
+    }
+}
+
+/// SVG document of `configuration`, highlighting `markers`.
+pub fn diagram(configuration: &Configuration, markers: &Markers) -> String {
+    let mut svg = String::new();
+    header(&mut svg);
+    board(&mut svg, configuration, markers);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Animated SVG document showing each configuration during `delay` milliseconds, looping
+/// forever. `markers` gives the markers of each configuration.
+pub fn animation(configurations: &[Configuration], markers: &[Markers], delay: u64) -> String {
+    let mut svg = String::new();
+    header(&mut svg);
+    let total = delay * configurations.len() as u64;
+    for (index, (configuration, markers)) in configurations.iter().zip(markers).enumerate() {
+        let start = delay * index as u64;
+        // keyTimes run from 0 to 1 over the whole animation
+        writeln!(
+            svg,
+            r#"<g visibility="hidden"><animate attributeName="visibility" values="hidden;visible;hidden" keyTimes="0;{:.6};{:.6}" calcMode="discrete" dur="{}ms" repeatCount="indefinite"/>"#,
+            start as f64 / total as f64,
+            (start + delay) as f64 / total as f64,
+            total
+        )
+        .unwrap();
+        board(&mut svg, configuration, markers);
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::Fen;
+
+    #[test]
+    fn diagrams() {
+        let fen: Fen = "r6b/8/2x2x2/8/8/8/8/b6r r 0".parse().unwrap();
+        let board = fen.board();
+        let before = fen.configuration(&board);
+        let svg = diagram(&before, &Markers::default());
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // background and cells
+        assert_eq!(svg.matches("<rect").count(), 1 + 64);
+        assert_eq!(svg.matches("fill=\"#404040\"").count(), 2);
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", RED)).count(), 2);
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", BLUE)).count(), 2);
+        // coordinates
+        assert_eq!(svg.matches("<text").count(), 16);
+        assert!(!svg.contains("<line") && !svg.contains("fill=\"none\""));
+
+        let jump = "a1-c1".parse().ok();
+        let svg = diagram(&before.play(&jump.unwrap()), &Markers::new(&before, &jump));
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert!(svg.contains("marker-end=\"url(#arrow)\""));
+        let duplication = "b2".parse().ok();
+        let svg = diagram(
+            &before.play(&duplication.unwrap()),
+            &Markers::new(&before, &duplication),
+        );
+        assert_eq!(svg.matches("fill=\"none\"").count(), 1);
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", RED)).count(), 3);
+    }
+
+    #[test]
+    fn animations() {
+        let fen: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+        let board = fen.board();
+        let first = fen.configuration(&board);
+        let movement = "b2".parse().ok();
+        let second = first.play(&movement.unwrap());
+        let markers = [Markers::default(), Markers::new(&first, &movement)];
+        let svg = animation(&[first, second], &markers, 500);
+        // one group per ply, shown in turn
+        assert_eq!(svg.matches("<g ").count(), 2);
+        assert_eq!(svg.matches("dur=\"1000ms\"").count(), 2);
+        assert!(svg.contains("keyTimes=\"0;0.000000;0.500000\""));
+        assert!(svg.contains("keyTimes=\"0;0.500000;1.000000\""));
+        assert_eq!(svg.matches("<rect").count(), 1 + 2 * 64);
+        assert_eq!(svg.matches("fill=\"none\"").count(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+//! Tournaments between several engines on several boards.
+//!
+//! Games are scheduled either as a round-robin (everyone meets everyone) or as a gauntlet
+//! (the first engine meets every other one). Every pairing is played twice per board with
+//! colours swapped. Games are played in parallel, except the ones involving timed engines : they
+//! are played one at a time so that they get the whole machine for their time.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
+use crate::configuration::Movement;
+use crate::notation::Fen;
+use crate::record::{GameRecord, RecordedMove, Termination};
+use crate::strategy::{EngineSpec, Registry};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// How to pair engines.
+pub enum Schedule {
+    /// Every engine plays every other engine.
+    RoundRobin,
+    /// The first engine plays every other engine.
+    Gauntlet,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "round-robin" | "roundrobin" => Ok(Schedule::RoundRobin),
+            "gauntlet" => Ok(Schedule::Gauntlet),
+            other => Err(format!("unknown schedule {}", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// One game to play: engine indices for each colour and the board file.
+pub struct Pairing {
+    /// Round number (starting at 1).
+    pub round: usize,
+    /// Index of the red engine.
+    pub red: usize,
+    /// Index of the blue engine.
+    pub blue: usize,
+    /// Board file name (inside the `boards` directory) or starting position.
+    pub board: String,
+}
+
+/// Compute all games for given schedule, number of engines, boards and rounds.
+pub fn pairings(
+    schedule: Schedule,
+    engines: usize,
+    boards: &[String],
+    rounds: usize,
+) -> Vec<Pairing> {
+    let pairs: Vec<(usize, usize)> = match schedule {
+        Schedule::RoundRobin => (0..engines)
+            .flat_map(|i| (i + 1..engines).map(move |j| (i, j)))
+            .collect(),
+        Schedule::Gauntlet => (1..engines).map(|j| (0, j)).collect(),
+    };
+    let mut games = Vec::new();
+    for round in 1..=rounds {
+        for board in boards {
+            for &(first, second) in &pairs {
+                for &(red, blue) in &[(first, second), (second, first)] {
+                    games.push(Pairing {
+                        round,
+                        red,
+                        blue,
+                        board: board.clone(),
+                    });
+                }
+            }
+        }
+    }
+    games
+}
+
+/// Result of one played game.
+pub struct GameResult {
+    /// What was played.
+    pub pairing: Pairing,
+    /// Starting position.
+    pub start: Fen,
+    /// All moves, `None` being a pass.
+    pub moves: Vec<Option<Movement>>,
+    /// Final red value (#red - #blue).
+    pub value: i8,
+    /// How the game ended if not on the board.
+    pub termination: Option<Termination>,
+}
+
+impl GameResult {
+    /// Score of the red player: 1 for a win, 0.5 for a draw, 0 for a loss.
+    pub fn red_score(&self) -> f64 {
+        match (self.termination, self.value) {
+            (Some(termination), _) if termination.loser() => 1.0,
+            (Some(_), _) => 0.0,
+            (None, x) if x > 0 => 1.0,
+            (None, x) if x < 0 => 0.0,
+            _ => 0.5,
+        }
+    }
+
+    /// Full record of this game.
+    pub fn record(&self, engines: &[EngineSpec]) -> GameRecord {
+        let board = self.start.board();
+        let mut record = GameRecord::new(
+            &self.start.configuration(&board),
+            &engines[self.pairing.red].label(),
+            &engines[self.pairing.blue].label(),
+        );
+        record.moves = self
+            .moves
+            .iter()
+            .map(|&movement| RecordedMove {
+                movement,
+                ..Default::default()
+            })
+            .collect();
+        record.result = Some(self.value);
+        record.termination = self.termination;
+        record
+    }
+}
+
+/// Play all given games in parallel.
+pub fn run(engines: &[EngineSpec], games: Vec<Pairing>) -> Result<Vec<GameResult>, String> {
+    let registry = Registry::default();
+    for engine in engines {
+        if !registry.engines().any(|(name, _)| name == engine.strategy) {
+            return Err(format!("unknown strategy {}", engine.strategy));
+        }
+    }
+    let boards = games
+        .iter()
+        .map(|g| g.board.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|name| {
+            let start = Fen::load(&name)?;
+            Ok((name, (start, start.board())))
+        })
+        .collect::<Result<HashMap<_, _>, String>>()?;
+    let play = |pairing: Pairing| {
+        let mut red = registry.build(&engines[pairing.red])?;
+        let mut blue = registry.build(&engines[pairing.blue])?;
+        let (start, ref board) = boards[&pairing.board];
+        let record = start.configuration(board).quiet_battle(&mut red, &mut blue);
+        Ok(GameResult {
+            pairing,
+            start,
+            moves: record.moves.iter().map(|m| m.movement).collect(),
+            value: record.result.unwrap_or_default(),
+            termination: record.termination,
+        })
+    };
+    let (timed, untimed): (Vec<_>, Vec<_>) = games
+        .into_iter()
+        .partition(|g| engines[g.red].time.is_some() || engines[g.blue].time.is_some());
+    let mut results = untimed
+        .into_par_iter()
+        .map(play)
+        .collect::<Result<Vec<_>, String>>()?;
+    for pairing in timed {
+        results.push(play(pairing)?);
+    }
+    Ok(results)
+}
+
+#[derive(Default, Clone)]
+/// Aggregated results of one engine.
+pub struct Standing {
+    /// Engine label.
+    pub name: String,
+    /// Games won.
+    pub wins: usize,
+    /// Games drawn.
+    pub draws: usize,
+    /// Games lost.
+    pub losses: usize,
+    /// Sum of final blob differences.
+    pub difference: i64,
+}
+
+impl Standing {
+    /// Points: 1 per win, 0.5 per draw.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+    /// Number of games played.
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// Final table of a tournament, sorted by points.
+pub struct Standings(pub Vec<Standing>);
+
+impl Standings {
+    /// Aggregate given results.
+    pub fn new(engines: &[EngineSpec], results: &[GameResult]) -> Self {
+        let mut standings: Vec<Standing> = engines
+            .iter()
+            .map(|e| Standing {
+                name: e.label(),
+                ..Default::default()
+            })
+            .collect();
+        for result in results {
+            let red = result.red_score();
+            for &(index, score, value) in &[
+                (result.pairing.red, red, result.value),
+                (result.pairing.blue, 1.0 - red, -result.value),
+            ] {
+                let standing = &mut standings[index];
+                match score {
+                    x if x > 0.5 => standing.wins += 1,
+                    x if x < 0.5 => standing.losses += 1,
+                    _ => standing.draws += 1,
+                }
+                standing.difference += value as i64;
+            }
+        }
+        standings.sort_by(|a, b| {
+            b.points()
+                .partial_cmp(&a.points())
+                .unwrap()
+                .then(b.difference.cmp(&a.difference))
+        });
+        Standings(standings)
+    }
+}
+
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        writeln!(
+            f,
+            "{:>3} {:<width$} {:>6} {:>5} {:>4} {:>4} {:>4} {:>6}",
+            "#",
+            "engine",
+            "points",
+            "games",
+            "won",
+            "draw",
+            "lost",
+            "diff",
+            width = width
+        )?;
+        for (rank, standing) in self.0.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3} {:<width$} {:>6.1} {:>5} {:>4} {:>4} {:>4} {:>+6}",
+                rank + 1,
+                standing.name,
+                standing.points(),
+                standing.games(),
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.difference,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Write one `GameRecord` file per game (`1.txt`, `2.txt`...) into given directory.
+pub fn write_games<P: AsRef<Path>>(
+    directory: P,
+    engines: &[EngineSpec],
+    results: &[GameResult],
+) -> io::Result<()> {
+    fs::create_dir_all(&directory)?;
+    for (index, result) in results.iter().enumerate() {
+        result
+            .record(engines)
+            .save(directory.as_ref().join(format!("{}.txt", index + 1)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::ForfeitReason;
+
+    fn boards() -> Vec<String> {
+        vec!["default".to_owned(), "fortress".to_owned()]
+    }
+
+    #[test]
+    fn round_robin_pairings() {
+        // 4 engines : 6 pairs, twice per board and round
+        let games = pairings(Schedule::RoundRobin, 4, &boards(), 3);
+        assert_eq!(games.len(), 6 * 2 * 2 * 3);
+        assert!(games.iter().all(|g| g.red != g.blue));
+        assert_eq!(games.last().unwrap().round, 3);
+    }
+
+    #[test]
+    fn gauntlet_pairings() {
+        let games = pairings(Schedule::Gauntlet, 4, &boards(), 1);
+        assert_eq!(games.len(), 3 * 2 * 2);
+        assert!(games.iter().all(|g| g.red == 0 || g.blue == 0));
+    }
+
+    #[test]
+    fn colours_are_swapped() {
+        let games = pairings(Schedule::RoundRobin, 3, &boards(), 1);
+        for game in &games {
+            assert_eq!(
+                games
+                    .iter()
+                    .filter(|g| g.board == game.board && g.red == game.blue && g.blue == game.red)
+                    .count(),
+                1
+            );
+        }
+    }
+
+    fn result(red: usize, blue: usize, value: i8) -> GameResult {
+        GameResult {
+            pairing: Pairing {
+                round: 1,
+                red,
+                blue,
+                board: "default".to_owned(),
+            },
+            start: "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap(),
+            moves: Vec::new(),
+            value,
+            termination: None,
+        }
+    }
+
+    #[test]
+    fn forfeits_lose_whatever_the_board() {
+        let engines: Vec<EngineSpec> = ["greedy", "minmax"]
+            .iter()
+            .map(|spec| spec.parse().unwrap())
+            .collect();
+        let forfeit = GameResult {
+            termination: Some(Termination::Forfeit(false, ForfeitReason::Timeout)),
+            ..result(0, 1, 5)
+        };
+        assert_eq!(forfeit.red_score(), 0.0);
+        assert_eq!(forfeit.record(&engines).winner(), Some(true));
+        let standings = Standings::new(&engines, &[forfeit]);
+        assert_eq!(standings.0[0].name, "minmax");
+        assert_eq!((standings.0[0].wins, standings.0[1].losses), (1, 1));
+    }
+
+    #[test]
+    fn standings_are_sorted_by_points_then_difference() {
+        let engines: Vec<EngineSpec> = ["greedy", "minmax", "alphabeta"]
+            .iter()
+            .map(|spec| spec.parse().unwrap())
+            .collect();
+        let results = [
+            result(0, 1, 3),
+            result(1, 0, 0),
+            result(1, 2, 10),
+            result(2, 1, -2),
+            result(0, 2, 0),
+            result(2, 0, 0),
+        ];
+        let standings = Standings::new(&engines, &results);
+        let table: Vec<_> = standings
+            .0
+            .iter()
+            .map(|s| (s.name.as_str(), s.points(), s.difference))
+            .collect();
+        // minmax and greedy have 2.5 points, minmax has the better difference
+        assert_eq!(
+            table,
+            vec![
+                ("minmax", 2.5, 9),
+                ("greedy", 2.5, 3),
+                ("alphabeta", 1.0, -12)
+            ]
+        );
+        assert!(standings.0.iter().all(|s| s.games() == 4));
+    }
+}
@@ -0,0 +1,111 @@
+//! `wasm-bindgen` API, for running games and engines in the browser.
+//!
+//! Build with `cargo build --target wasm32-unknown-unknown --features wasm`. Only fixed depth
+//! single-threaded engines are available (no time control, no `minmaxpar`).
+use wasm_bindgen::prelude::*;
+
+use crate::configuration::parse_move;
+use crate::notation::Fen;
+use crate::strategy::EngineSpec;
+
+#[wasm_bindgen]
+/// A game in progress.
+pub struct Game {
+    position: Fen,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    /// Start from given position notation (see `notation`).
+    pub fn new(position: &str) -> Result<Game, JsValue> {
+        Ok(Game {
+            position: position
+                .parse()
+                .map_err(|e: String| JsValue::from_str(&e))?,
+        })
+    }
+
+    /// Standard starting position.
+    pub fn standard() -> Game {
+        Game {
+            position: "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap(),
+        }
+    }
+
+    /// Current position notation.
+    pub fn position(&self) -> String {
+        self.position.to_string()
+    }
+
+    /// 64 cells, row 1 first : `x` holes, `r` red, `b` blue, `.` empty.
+    pub fn cells(&self) -> String {
+        let board = self.position.board();
+        self.position
+            .configuration(&board)
+            .serialize()
+            .chars()
+            .skip(1)
+            .map(|c| match c {
+                'h' => 'x',
+                ' ' => '.',
+                c => c,
+            })
+            .collect()
+    }
+
+    /// `"red"` or `"blue"`.
+    pub fn to_move(&self) -> String {
+        if self.position.current_player {
+            "blue"
+        } else {
+            "red"
+        }
+        .to_owned()
+    }
+
+    /// Legal moves in algebraic notation.
+    pub fn legal_moves(&self) -> Vec<String> {
+        let board = self.position.board();
+        self.position
+            .configuration(&board)
+            .movements()
+            .map(|m| m.to_string())
+            .collect()
+    }
+
+    /// Is the game over ?
+    pub fn game_over(&self) -> bool {
+        let board = self.position.board();
+        self.position.configuration(&board).game_over()
+    }
+
+    /// Number of red blobs minus number of blue blobs.
+    pub fn red_value(&self) -> i8 {
+        let board = self.position.board();
+        self.position.configuration(&board).red_value()
+    }
+
+    /// Play given move (`b2`, `a1-c3` or `pass`).
+    pub fn play(&mut self, movement: &str) -> Result<(), JsValue> {
+        let movement = parse_move(movement).map_err(|e| JsValue::from_str(&e))?;
+        let board = self.position.board();
+        let configuration = self.position.configuration(&board);
+        let next = match movement {
+            Some(m) if configuration.check_move(&m) => configuration.play(&m),
+            Some(m) => return Err(JsValue::from_str(&format!("{} is not a legal move", m))),
+            None => configuration.skip_play(),
+        };
+        self.position = Fen::from_configuration(&next, self.position.ply + 1);
+        Ok(())
+    }
+
+    /// Move chosen by given engine spec (like `alphabeta:depth=4`), `"pass"` if none.
+    pub fn engine_move(&self, spec: &str) -> Result<String, JsValue> {
+        let spec: EngineSpec = spec.parse().map_err(|e: String| JsValue::from_str(&e))?;
+        let mut engine = spec.build().map_err(|e| JsValue::from_str(&e))?;
+        let board = self.position.board();
+        let movement = engine.compute_next_move(&self.position.configuration(&board));
+        Ok(crate::configuration::format_move(&movement))
+    }
+}
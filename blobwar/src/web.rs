@@ -0,0 +1,459 @@
+//! Local web interface : a small HTTP server playing games in the browser.
+//!
+//! Endpoints (all answers are JSON except the page itself and the event stream) :
+//! - `GET /` : the board page ;
+//! - `POST /games` with `{"red": SPEC, "blue": SPEC, "board": BOARD}` : start a game, sides
+//!   with the `human` spec are played from the browser, others by one of the search engines
+//!   (`greedy`, `minmax`, `alphabeta`...) ;
+//! - `GET /games/ID` : current state ;
+//! - `GET /games/ID/moves` : legal moves ;
+//! - `POST /games/ID/move` with `{"move": "b2"}` : play a move for a human side ;
+//! - `GET /games/ID/events` : server-sent events, one state per move played.
+//!
+//! Engine moves are computed in a background thread as soon as an engine has to play.
+//! Finished games are forgotten a while after their end.
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::board::Board;
+use crate::configuration::{format_move, parse_move, Configuration, Movement};
+use crate::notation::Fen;
+use crate::strategy::registry::SEARCH_ENGINES;
+use crate::strategy::EngineSpec;
+
+const PAGE: &str = include_str!("web.html");
+
+/// Largest request body we accept.
+const MAX_BODY: usize = 64 * 1024;
+
+/// How long a browser may take to read an event before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long finished games stay available.
+const KEEP_FINISHED: Duration = Duration::from_secs(600);
+
+/// One game in progress.
+struct Game {
+    board: Board,
+    position: Fen,
+    /// Red and blue players, `None` for humans in the browser.
+    players: [Option<EngineSpec>; 2],
+    moves: Vec<Option<Movement>>,
+    /// Event stream subscribers, each one written to by its own thread.
+    listeners: Vec<Sender<String>>,
+    /// Set while an engine thread is computing a move.
+    thinking: bool,
+    /// Last engine failure.
+    error: Option<String>,
+    /// When the game ended (or an engine failed).
+    finished: Option<Instant>,
+}
+
+#[derive(Serialize)]
+/// What the browser sees of a game.
+struct State {
+    id: usize,
+    position: String,
+    /// 64 cells, row 1 first : `x` holes, `r` red, `b` blue, `.` empty.
+    cells: String,
+    to_move: &'static str,
+    players: [String; 2],
+    moves: Vec<String>,
+    legal_moves: Vec<String>,
+    thinking: bool,
+    game_over: bool,
+    red_value: i8,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewGame {
+    red: Option<String>,
+    blue: Option<String>,
+    board: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PlayMove {
+    #[serde(rename = "move")]
+    movement: String,
+}
+
+impl Game {
+    fn configuration(&self) -> Configuration<'_> {
+        self.position.configuration(&self.board)
+    }
+
+    fn state(&self, id: usize) -> State {
+        let configuration = self.configuration();
+        let legal_moves: Vec<String> = configuration.movements().map(|m| m.to_string()).collect();
+        State {
+            id,
+            position: self.position.to_string(),
+            cells: configuration
+                .serialize()
+                .chars()
+                .skip(1)
+                .map(|c| match c {
+                    'h' => 'x',
+                    ' ' => '.',
+                    c => c,
+                })
+                .collect(),
+            to_move: if configuration.current_player {
+                "blue"
+            } else {
+                "red"
+            },
+            players: [0, 1].map(|side| {
+                self.players[side]
+                    .as_ref()
+                    .map_or_else(|| "human".to_owned(), |spec| spec.label())
+            }),
+            moves: self.moves.iter().map(format_move).collect(),
+            legal_moves,
+            thinking: self.thinking,
+            game_over: configuration.game_over(),
+            red_value: configuration.red_value(),
+            error: self.error.clone(),
+        }
+    }
+
+    /// Play given move (already validated) and notify listeners.
+    fn play(&mut self, id: usize, movement: Option<Movement>) {
+        let configuration = self.configuration();
+        let next = movement.map_or_else(|| configuration.skip_play(), |m| configuration.play(&m));
+        self.position = Fen::from_configuration(&next, self.position.ply + 1);
+        self.moves.push(movement);
+        self.broadcast(id);
+    }
+
+    fn broadcast(&mut self, id: usize) {
+        if self.finished.is_none() && (self.error.is_some() || self.configuration().game_over()) {
+            self.finished = Some(Instant::now());
+        }
+        let event = event(&self.state(id));
+        self.listeners
+            .retain(|listener| listener.send(event.clone()).is_ok());
+    }
+
+    /// Engine playing now, if any.
+    fn engine_to_play(&self) -> Option<EngineSpec> {
+        let configuration = self.configuration();
+        if configuration.game_over() {
+            None
+        } else {
+            self.players[configuration.current_player as usize].clone()
+        }
+    }
+}
+
+fn event(state: &State) -> String {
+    format!(
+        "data: {}\n\n",
+        serde_json::to_string(state).expect("failed serializing state")
+    )
+}
+
+type Games = Arc<Mutex<HashMap<usize, Game>>>;
+
+/// Let engines play in game `id` until a human has to play.
+fn advance(games: Games, id: usize) {
+    loop {
+        let (spec, position) = {
+            let mut games = games.lock().unwrap();
+            let game = games.get_mut(&id).expect("unknown game");
+            let spec = match game.engine_to_play() {
+                Some(spec) if !game.thinking => spec,
+                _ => return,
+            };
+            game.thinking = true;
+            (spec, game.position)
+        };
+        let board = position.board();
+        let configuration = position.configuration(&board);
+        let result = spec
+            .build()
+            .map(|mut engine| engine.compute_next_move(&configuration));
+        let mut games = games.lock().unwrap();
+        let game = games.get_mut(&id).expect("unknown game");
+        game.thinking = false;
+        match result {
+            Ok(Some(movement)) if !configuration.check_move(&movement) => {
+                game.error = Some(format!("{} played illegal move {}", spec, movement));
+                game.broadcast(id);
+                return;
+            }
+            Ok(movement) => game.play(id, movement),
+            Err(e) => {
+                game.error = Some(e);
+                game.broadcast(id);
+                return;
+            }
+        }
+    }
+}
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let method = words
+        .next()
+        .ok_or_else(|| invalid("empty request"))?
+        .to_owned();
+    let path = words
+        .next()
+        .ok_or_else(|| invalid("missing path"))?
+        .to_owned();
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("invalid length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(invalid("body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid("body is not utf-8"))?;
+    Ok(Request { method, path, body })
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+fn json<T: serde::Serialize>(value: &T) -> (&'static str, String) {
+    (
+        "200 OK",
+        serde_json::to_string(value).expect("failed serializing answer"),
+    )
+}
+
+fn error(status: &'static str, message: &str) -> (&'static str, String) {
+    (status, serde_json::json!({ "error": message }).to_string())
+}
+
+/// The web server.
+pub struct WebServer {
+    games: Games,
+    next_id: Mutex<usize>,
+}
+
+impl Default for WebServer {
+    fn default() -> Self {
+        WebServer {
+            games: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Mutex::new(1),
+        }
+    }
+}
+
+impl WebServer {
+    /// Serve requests on given address forever.
+    pub fn serve<A: ToSocketAddrs>(self, address: A) -> io::Result<()> {
+        self.serve_listener(TcpListener::bind(address)?)
+    }
+
+    /// Serve requests coming to given listener forever.
+    pub fn serve_listener(self, listener: TcpListener) -> io::Result<()> {
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = server.clone();
+            thread::spawn(move || {
+                if let Err(e) = server.handle(stream) {
+                    eprintln!("web: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let request = read_request(&stream)?;
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let (status, body) = match (request.method.as_str(), segments.as_slice()) {
+            ("GET", [""]) => return respond(&mut stream, "200 OK", "text/html", PAGE),
+            ("POST", ["games"]) => self.new_game(&request.body),
+            (method, ["games", id, rest @ ..]) => match id.parse() {
+                Err(_) => error("404 Not Found", "invalid game id"),
+                Ok(id) => match (method, rest) {
+                    ("GET", []) => self.with_game(id, |game| json(&game.state(id))),
+                    ("GET", ["moves"]) => {
+                        self.with_game(id, |game| json(&game.state(id).legal_moves))
+                    }
+                    ("POST", ["move"]) => self.play(id, &request.body),
+                    ("GET", ["events"]) => return self.subscribe(id, stream),
+                    _ => error("404 Not Found", "unknown endpoint"),
+                },
+            },
+            _ => error("404 Not Found", "unknown endpoint"),
+        };
+        respond(&mut stream, status, "application/json", &body)
+    }
+
+    fn with_game<F: FnOnce(&mut Game) -> (&'static str, String)>(
+        &self,
+        id: usize,
+        action: F,
+    ) -> (&'static str, String) {
+        match self.games.lock().unwrap().get_mut(&id) {
+            Some(game) => action(game),
+            None => error("404 Not Found", "unknown game"),
+        }
+    }
+
+    fn new_game(&self, body: &str) -> (&'static str, String) {
+        let request: NewGame = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(e) => return error("400 Bad Request", &e.to_string()),
+        };
+        let mut players = [None, None];
+        for (player, spec) in players.iter_mut().zip([request.red, request.blue]) {
+            let spec = match spec.as_deref().unwrap_or("human").parse::<EngineSpec>() {
+                Ok(spec) => spec,
+                Err(e) => return error("400 Bad Request", &e),
+            };
+            // played from the browser
+            if spec
+                == (EngineSpec {
+                    strategy: "human".to_owned(),
+                    ..Default::default()
+                })
+            {
+                continue;
+            }
+            if !SEARCH_ENGINES.contains(&spec.strategy.as_str()) {
+                return error(
+                    "400 Bad Request",
+                    &format!("{} cannot play on the web", spec.strategy),
+                );
+            }
+            if let Err(e) = spec.build() {
+                return error("400 Bad Request", &e);
+            }
+            *player = Some(spec);
+        }
+        let position = match Fen::load(request.board.as_deref().unwrap_or("standard")) {
+            Ok(position) => position,
+            Err(e) => return error("400 Bad Request", &e),
+        };
+        let game = Game {
+            board: position.board(),
+            position,
+            players,
+            moves: Vec::new(),
+            listeners: Vec::new(),
+            thinking: false,
+            error: None,
+            finished: None,
+        };
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id - 1
+        };
+        let state = game.state(id);
+        let mut games = self.games.lock().unwrap();
+        games.retain(|_, game| game.finished.is_none_or(|f| f.elapsed() < KEEP_FINISHED));
+        games.insert(id, game);
+        drop(games);
+        let games = self.games.clone();
+        thread::spawn(move || advance(games, id));
+        json(&state)
+    }
+
+    fn play(&self, id: usize, body: &str) -> (&'static str, String) {
+        let request: PlayMove = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(e) => return error("400 Bad Request", &e.to_string()),
+        };
+        let movement = match parse_move(&request.movement) {
+            Ok(movement) => movement,
+            Err(e) => return error("400 Bad Request", &e),
+        };
+        let answer = self.with_game(id, |game| {
+            let configuration = game.configuration();
+            if configuration.game_over() {
+                return error("409 Conflict", "game is over");
+            }
+            if game.players[configuration.current_player as usize].is_some() {
+                return error("409 Conflict", "an engine is playing now");
+            }
+            let legal = match movement {
+                Some(m) => configuration.check_move(&m),
+                None => configuration.movements().next().is_none(),
+            };
+            if !legal {
+                return error("400 Bad Request", "illegal move");
+            }
+            game.play(id, movement);
+            json(&game.state(id))
+        });
+        let games = self.games.clone();
+        thread::spawn(move || advance(games, id));
+        answer
+    }
+
+    /// Keep the connection open and send it every new state, until the game is forgotten or the
+    /// browser stops reading.
+    fn subscribe(&self, id: usize, mut stream: TcpStream) -> io::Result<()> {
+        let (sender, events) = channel();
+        let first = match self.games.lock().unwrap().get_mut(&id) {
+            Some(game) => {
+                game.listeners.push(sender);
+                event(&game.state(id))
+            }
+            None => {
+                let (status, body) = error("404 Not Found", "unknown game");
+                return respond(&mut stream, status, "application/json", &body);
+            }
+        };
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n"
+        )?;
+        stream.write_all(first.as_bytes())?;
+        for event in events {
+            stream.write_all(event.as_bytes())?;
+        }
+        Ok(())
+    }
+}
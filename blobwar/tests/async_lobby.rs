@@ -0,0 +1,166 @@
+//! Many clients playing concurrently on the asynchronous lobby, over loopback sockets.
+//! Run with `cargo test --features async`.
+#![cfg(feature = "async")]
+
+use blobwar::asynchronous::{AsyncConnection, AsyncLobby, MAX_MESSAGE};
+use blobwar::lobby::LobbySettings;
+use blobwar::notation::Fen;
+use blobwar::protocol::Message;
+use blobwar::strategy::{Greedy, Strategy};
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Start a lobby, stopped by sending on the returned channel.
+async fn lobby(
+    clock: Option<Duration>,
+) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<AsyncLobby>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel();
+    let lobby = AsyncLobby::new(LobbySettings {
+        clock,
+        ..Default::default()
+    })
+    .unwrap();
+    let server = tokio::spawn(async move {
+        lobby
+            .clone()
+            .serve(listener, async {
+                let _ = stopped.await;
+            })
+            .await
+            .unwrap();
+        lobby
+    });
+    (address, stop, server)
+}
+
+async fn connect(address: SocketAddr, name: &str) -> AsyncConnection {
+    let stream = TcpStream::connect(address).await.unwrap();
+    let mut connection = AsyncConnection::new(stream).unwrap();
+    assert_eq!(connection.handshake(name).await.unwrap(), "blobwar lobby");
+    connection
+}
+
+/// Pair and play greedy moves for given number of games.
+async fn play(address: SocketAddr, name: String, games: usize) {
+    let mut connection = connect(address, &name).await;
+    let mut strategy = Greedy();
+    for _ in 0..games {
+        connection.send(&Message::Pair).await.unwrap();
+        loop {
+            match connection.receive().await.unwrap() {
+                Message::Position { position, .. } => {
+                    let position: Fen = position.parse().unwrap();
+                    let board = position.board();
+                    let movement = strategy.compute_next_move(&position.configuration(&board));
+                    connection.send(&Message::Move { movement }).await.unwrap();
+                }
+                Message::GameOver { termination, .. } => {
+                    assert_eq!(termination, None);
+                    break;
+                }
+                Message::NewGame { .. } => {}
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn many_concurrent_games() {
+    let (address, stop, server) = lobby(Some(Duration::from_secs(30))).await;
+    let clients: Vec<_> = (0..32)
+        .map(|i| tokio::spawn(play(address, format!("client {}", i), 3)))
+        .collect();
+    for client in clients {
+        client.await.unwrap();
+    }
+
+    let mut connection = connect(address, "watcher").await;
+    connection.send(&Message::GetLeaderboard).await.unwrap();
+    match connection.receive().await.unwrap() {
+        Message::Leaderboard { entries } => {
+            assert_eq!(entries.len(), 32);
+            assert!(entries.iter().all(|e| e.wins + e.draws + e.losses == 3));
+        }
+        other => panic!("expected leaderboard, got {:?}", other),
+    }
+    stop.send(()).unwrap();
+    let lobby = server.await.unwrap();
+    assert_eq!(lobby.leaderboard().0.len(), 32);
+}
+
+#[tokio::test]
+async fn shutdown_cancels_games() {
+    let (address, stop, server) = lobby(None).await;
+    let mut first = connect(address, "first").await;
+    let mut second = connect(address, "second").await;
+    first.send(&Message::Pair).await.unwrap();
+    // make sure first is the one waiting
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    second.send(&Message::Pair).await.unwrap();
+    // red (first) never answers
+    assert!(matches!(
+        first.receive().await.unwrap(),
+        Message::NewGame { .. }
+    ));
+    assert!(matches!(
+        first.receive().await.unwrap(),
+        Message::Position { .. }
+    ));
+
+    stop.send(()).unwrap();
+    server.await.unwrap();
+    for connection in [&mut first, &mut second] {
+        assert_eq!(
+            connection.receive().await.unwrap(),
+            Message::Error {
+                message: "server shutting down".to_owned()
+            }
+        );
+    }
+}
+
+#[tokio::test]
+async fn long_messages_are_refused() {
+    let (address, stop, server) = lobby(None).await;
+    let stream = TcpStream::connect(address).await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut hello = String::new();
+    reader.read_line(&mut hello).await.unwrap();
+    assert!(hello.contains("hello"));
+    // the lobby may stop reading (and reset the connection) before the end
+    let _ = writer.write_all(&vec![b'x'; 2 * MAX_MESSAGE]).await;
+    // the lobby gave up on us
+    let mut rest = Vec::new();
+    let closed = reader.read_to_end(&mut rest).await;
+    assert!(closed.is_err() || rest.is_empty());
+    stop.send(()).unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn names_are_unique() {
+    let (address, stop, server) = lobby(None).await;
+    let mut first = connect(address, "same").await;
+    let mut second = connect(address, "same").await;
+    assert!(matches!(
+        second.receive().await.unwrap(),
+        Message::Error { .. }
+    ));
+    first.send(&Message::GetLeaderboard).await.unwrap();
+    assert!(matches!(
+        first.receive().await.unwrap(),
+        Message::Leaderboard { .. }
+    ));
+    stop.send(()).unwrap();
+    server.await.unwrap();
+}
@@ -0,0 +1,181 @@
+//! External engines driven through the text engine protocol.
+use blobwar::engine::{Command, Limit, Reply};
+use blobwar::notation::Fen;
+use blobwar::record::{ForfeitReason, GameRecord, Termination};
+use blobwar::strategy::{Greedy, ProcessEngine, Request, Strategy};
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Child, Command as Process};
+use std::thread::sleep;
+use std::time::Duration;
+
+const ENGINE: &str = env!("CARGO_BIN_EXE_engine");
+
+fn play(engine: ProcessEngine) -> GameRecord {
+    let start: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+    let board = start.board();
+    start.configuration(&board).battle(engine, Greedy())
+}
+
+/// Write a fake engine answering `go` with given line.
+fn script(name: &str, answer: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("blobwar-{}-{}.sh", name, std::process::id()));
+    fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\nwhile read line; do case $line in\n\
+             blobwar) echo id name {}; echo blobwarok;;\n\
+             go*) {};;\n\
+             quit) exit;;\n\
+             esac; done\n",
+            name, answer
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn commands_and_replies_round_trip() {
+    let commands = [
+        Command::Blobwar,
+        Command::SetOption {
+            name: "strategy".to_owned(),
+            value: "alphabeta".to_owned(),
+        },
+        Command::Position("r6b/8/8/8/8/8/8/b6r b 3".parse().unwrap()),
+        Command::Go(None),
+        Command::Go(Some(Limit::Depth(3))),
+        Command::Go(Some(Limit::MoveTime(500))),
+        Command::Go(Some(Limit::Infinite)),
+        Command::Quit,
+    ];
+    for command in &commands {
+        assert_eq!(&command.to_string().parse::<Command>().unwrap(), command);
+    }
+    let replies = [
+        Reply::Id("alphabeta:depth=4".to_owned()),
+        Reply::Info("string searching".to_owned()),
+        Reply::BestMove(Some("a1-c3".parse().unwrap())),
+        Reply::BestMove(None),
+    ];
+    for reply in &replies {
+        assert_eq!(&reply.to_string().parse::<Reply>().unwrap(), reply);
+    }
+    assert!("go depth".parse::<Command>().is_err());
+    assert!("position nowhere".parse::<Command>().is_err());
+}
+
+#[test]
+fn engine_over_stdin_and_stdout() {
+    let mut engine = ProcessEngine::spawn(&format!("{} greedy", ENGINE)).unwrap();
+    engine.set_option("strategy", "alphabeta").unwrap();
+    let engine = engine.limit(Some(Limit::Depth(1)));
+    assert_eq!(engine.to_string(), "External engine : greedy");
+    assert_eq!(play(engine).termination, None);
+}
+
+#[test]
+fn engine_on_a_unix_socket() {
+    let path = env::temp_dir().join(format!("blobwar-engine-{}.sock", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut server: Child = Process::new(ENGINE)
+        .arg("--socket")
+        .arg(&path)
+        .arg("greedy")
+        .spawn()
+        .unwrap();
+    while !path.exists() {
+        sleep(Duration::from_millis(10));
+    }
+    // the engine serves one controller after the other
+    for _ in 0..2 {
+        let engine = ProcessEngine::connect(&path).unwrap();
+        assert_eq!(play(engine).termination, None);
+    }
+    server.kill().unwrap();
+    server.wait().unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn illegal_moves_forfeit() {
+    let path = script("illegal", "echo bestmove a1");
+    let engine = ProcessEngine::spawn(path.to_str().unwrap()).unwrap();
+    assert_eq!(
+        play(engine).termination,
+        Some(Termination::Forfeit(false, ForfeitReason::IllegalMove))
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn silent_engines_forfeit() {
+    let path = script("silent", "true");
+    let engine = ProcessEngine::spawn(path.to_str().unwrap())
+        .unwrap()
+        .limit(Some(Limit::MoveTime(50)));
+    assert_eq!(
+        play(engine).termination,
+        Some(Termination::Forfeit(false, ForfeitReason::Timeout))
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn silent_engines_forfeit_at_fixed_depth() {
+    let path = script("silent-depth", "true");
+    let engine = ProcessEngine::spawn(path.to_str().unwrap())
+        .unwrap()
+        .limit(Some(Limit::Depth(3)))
+        .timeout(Some(Duration::from_millis(50)));
+    assert_eq!(
+        play(engine).termination,
+        Some(Termination::Forfeit(false, ForfeitReason::Timeout))
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn quiet_games_end_on_forfeits() {
+    let path = script("silent-quiet", "true");
+    let engine = ProcessEngine::spawn(path.to_str().unwrap())
+        .unwrap()
+        .limit(Some(Limit::MoveTime(50)));
+    let start: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+    let board = start.board();
+    let mut configuration = start.configuration(&board);
+    assert_eq!(configuration.battle_no_log(engine, Greedy()), -i8::MAX);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn late_answers_are_not_taken_for_the_next_search() {
+    // the first search answers once we gave up on it
+    let path = script(
+        "late",
+        "n=$((n+1)); if [ $n = 1 ]; then sleep 3; echo bestmove b2; else echo bestmove a1-c1; fi",
+    );
+    let mut engine = ProcessEngine::spawn(path.to_str().unwrap())
+        .unwrap()
+        .timeout(Some(Duration::from_millis(50)));
+    let start: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+    let board = start.board();
+    let configuration = start.configuration(&board);
+    assert_eq!(engine.compute_next_move(&configuration), None);
+    assert_eq!(
+        engine.request(),
+        Some(Request::Forfeit(ForfeitReason::Timeout))
+    );
+
+    let mut engine = engine.timeout(Some(Duration::from_secs(5)));
+    assert_eq!(
+        engine.compute_next_move(&configuration),
+        Some("a1-c1".parse().unwrap())
+    );
+    fs::remove_file(path).unwrap();
+}
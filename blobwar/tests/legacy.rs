@@ -0,0 +1,63 @@
+//! Clients of the first versions (serialized configurations and derived JSON moves), over
+//! loopback sockets.
+use blobwar::configuration::Movement;
+use blobwar::notation::Fen;
+use blobwar::record::{ForfeitReason, Termination};
+use blobwar::strategy::{Greedy, NetworkPlayer};
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+#[test]
+fn baseline_payloads() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let game = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let remote = NetworkPlayer::legacy(stream).unwrap();
+        let start: Fen = "r6b/8/8/8/8/8/8/b6r r 0".parse().unwrap();
+        let board = start.board();
+        start.configuration(&board).battle(remote, Greedy())
+    });
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let mut configurations = BufReader::new(client.try_clone().unwrap()).lines();
+    let first = configurations.next().unwrap().unwrap();
+    assert_eq!(first.len(), 65);
+    assert!(first.starts_with("0r"));
+    // a1 jumps to a3
+    client.write_all(b"{\"Jump\":[0,16]}\n").unwrap();
+    let second = configurations.next().unwrap().unwrap();
+    assert!(second.starts_with('0'));
+    assert_eq!(second.chars().nth(1), Some(' '));
+    assert_eq!(second.chars().nth(17), Some('r'));
+    // a3 duplicates on b3
+    client.write_all(b"{\"Duplicate\":17}\n").unwrap();
+    configurations.next().unwrap().unwrap();
+    // the new text form is not the legacy protocol
+    client.write_all(b"\"a3-c3\"\n").unwrap();
+
+    let record = game.join().unwrap();
+    assert_eq!(record.moves[0].movement, Some(Movement::Jump(0, 16)));
+    assert_eq!(record.moves[2].movement, Some(Movement::Duplicate(17)));
+    assert_eq!(
+        record.termination,
+        Some(Termination::Forfeit(false, ForfeitReason::Disconnection))
+    );
+}
+
+#[test]
+fn moves_keep_their_derived_form() {
+    // what `client --legacy` sends
+    assert_eq!(
+        serde_json::to_string(&Some(Movement::Jump(3, 17))).unwrap(),
+        "{\"Jump\":[3,17]}"
+    );
+    assert_eq!(
+        serde_json::to_string(&Some(Movement::Duplicate(9))).unwrap(),
+        "{\"Duplicate\":9}"
+    );
+    assert_eq!(serde_json::to_string(&None::<Movement>).unwrap(), "null");
+}
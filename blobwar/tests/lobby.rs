@@ -0,0 +1,146 @@
+//! Clients listing, creating, joining and pairing games in a lobby, over loopback sockets.
+use blobwar::lobby::{Lobby, LobbySettings};
+use blobwar::notation::Fen;
+use blobwar::protocol::{Connection, Message, OpenGame};
+use blobwar::strategy::{Greedy, Strategy};
+
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+const BOARD: &str = "r6b/8/8/8/8/8/8/b6r r 0";
+
+fn lobby() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let settings = LobbySettings {
+        board: BOARD.to_owned(),
+        ..Default::default()
+    };
+    thread::spawn(move || Lobby::new(settings).serve_listener(listener));
+    address
+}
+
+fn connect(address: SocketAddr, name: &str) -> Connection {
+    let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+    assert_eq!(connection.handshake(name).unwrap(), "blobwar lobby");
+    connection
+}
+
+fn ask(connection: &mut Connection, message: Message) -> Message {
+    connection.send(&message).unwrap();
+    connection.receive().unwrap()
+}
+
+/// Play greedy moves until the game is over, in the background.
+fn play(mut connection: Connection) -> thread::JoinHandle<Connection> {
+    thread::spawn(move || {
+        let mut strategy = Greedy();
+        loop {
+            match connection.receive().unwrap() {
+                Message::Position { position, .. } => {
+                    let position: Fen = position.parse().unwrap();
+                    let board = position.board();
+                    let movement = strategy.compute_next_move(&position.configuration(&board));
+                    connection.send(&Message::Move { movement }).unwrap();
+                }
+                Message::GameOver { termination, .. } => {
+                    assert_eq!(termination, None);
+                    return connection;
+                }
+                Message::NewGame { .. } => {}
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+    })
+}
+
+fn leaderboard(connection: &mut Connection) -> Vec<(String, usize)> {
+    match ask(connection, Message::GetLeaderboard) {
+        Message::Leaderboard { entries } => entries
+            .into_iter()
+            .map(|e| (e.name, e.wins + e.draws + e.losses))
+            .collect(),
+        other => panic!("expected leaderboard, got {:?}", other),
+    }
+}
+
+#[test]
+fn create_and_join_a_game() {
+    let address = lobby();
+    let mut host = connect(address, "host");
+    assert_eq!(
+        ask(&mut host, Message::ListGames),
+        Message::Games { games: vec![] }
+    );
+    assert_eq!(leaderboard(&mut host), vec![]);
+    let id = match ask(&mut host, Message::CreateGame { board: None }) {
+        Message::GameCreated { id } => id,
+        other => panic!("expected game created, got {:?}", other),
+    };
+    let host = play(host);
+
+    let mut guest = connect(address, "guest");
+    assert_eq!(
+        ask(&mut guest, Message::ListGames),
+        Message::Games {
+            games: vec![OpenGame {
+                id,
+                host: "host".to_owned(),
+                board: BOARD.to_owned(),
+            }]
+        }
+    );
+    assert!(matches!(
+        ask(&mut guest, Message::JoinGame { id: id + 1 }),
+        Message::Error { .. }
+    ));
+    guest.send(&Message::JoinGame { id }).unwrap();
+    let mut guest = play(guest).join().unwrap();
+    let mut host = host.join().unwrap();
+
+    // both are back in the lobby
+    let mut players = leaderboard(&mut guest);
+    players.sort();
+    assert_eq!(
+        players,
+        vec![("guest".to_owned(), 1), ("host".to_owned(), 1)]
+    );
+    assert_eq!(
+        ask(&mut host, Message::ListGames),
+        Message::Games { games: vec![] }
+    );
+}
+
+#[test]
+fn pair_clients() {
+    let address = lobby();
+    let mut first = connect(address, "first");
+    first.send(&Message::Pair).unwrap();
+    let first = play(first);
+    let mut second = connect(address, "second");
+    second.send(&Message::Pair).unwrap();
+    let mut second = play(second).join().unwrap();
+    first.join().unwrap();
+    assert_eq!(leaderboard(&mut second).len(), 2);
+}
+
+#[test]
+fn names_are_taken_until_their_client_leaves() {
+    let address = lobby();
+    let first = connect(address, "same");
+    let mut second = connect(address, "same");
+    assert!(matches!(second.receive().unwrap(), Message::Error { .. }));
+
+    drop(first);
+    // the lobby notices on its next read
+    let welcomed = (0..50).any(|_| {
+        let mut third = connect(address, "same");
+        let welcomed = matches!(ask(&mut third, Message::ListGames), Message::Games { .. });
+        if !welcomed {
+            thread::sleep(Duration::from_millis(20));
+        }
+        welcomed
+    });
+    assert!(welcomed);
+}
@@ -0,0 +1,154 @@
+//! Clients reconnecting to a server after losing their connection, over loopback sockets.
+use blobwar::notation::Fen;
+use blobwar::protocol::{Connection, Message};
+use blobwar::record::{ForfeitReason, GameRecord, Termination};
+use blobwar::session::Sessions;
+use blobwar::strategy::{Greedy, NetworkPlayer, Strategy};
+
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Not the starting position of its board : blue already duplicated.
+const START: &str = "r6b/8/8/8/8/8/1b6/b6r r 0";
+
+/// Start a server where a resumable remote player (red) plays greedy (blue).
+fn server(grace: Duration, timeout: Option<Duration>) -> (SocketAddr, JoinHandle<GameRecord>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let game = thread::spawn(move || {
+        let sessions = Sessions::new(grace);
+        let (stream, _) = listener.accept().unwrap();
+        let remote = NetworkPlayer::new(stream)
+            .unwrap()
+            .resumable(&sessions)
+            .unwrap();
+        remote.set_timeout(timeout).unwrap();
+        sessions.serve(listener);
+        let start: Fen = START.parse().unwrap();
+        let board = start.board();
+        start.configuration(&board).battle(remote, Greedy())
+    });
+    (address, game)
+}
+
+fn connect(address: SocketAddr) -> Connection {
+    let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+    connection.handshake("test client").unwrap();
+    connection
+}
+
+/// Play greedy moves until the game is over.
+fn play_until_over(connection: &mut Connection) {
+    let mut strategy = Greedy();
+    loop {
+        match connection.receive().unwrap() {
+            Message::Position { position, .. } => {
+                let position: Fen = position.parse().unwrap();
+                let board = position.board();
+                let movement = strategy.compute_next_move(&position.configuration(&board));
+                connection.send(&Message::Move { movement }).unwrap();
+            }
+            Message::GameOver { termination, .. } => {
+                assert_eq!(termination, None);
+                return;
+            }
+            Message::NewGame { .. } => {}
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn resume_after_losing_the_connection() {
+    let (address, game) = server(Duration::from_secs(5), Some(Duration::from_secs(30)));
+    let mut connection = connect(address);
+    let token = match connection.receive().unwrap() {
+        Message::Session { token, grace } => {
+            assert_eq!(grace, 5000);
+            token
+        }
+        other => panic!("expected session, got {:?}", other),
+    };
+    match connection.receive().unwrap() {
+        Message::NewGame { board, .. } => assert_eq!(board, START),
+        other => panic!("expected new game, got {:?}", other),
+    }
+    let position = match connection.receive().unwrap() {
+        Message::Position { position, clock } => {
+            assert!(clock.unwrap() <= 30_000);
+            position
+        }
+        other => panic!("expected position, got {:?}", other),
+    };
+    drop(connection);
+
+    let mut connection = connect(address);
+    connection
+        .send(&Message::Resume {
+            token: token.clone(),
+        })
+        .unwrap();
+    match connection.receive().unwrap() {
+        Message::Session { token: resumed, .. } => assert_eq!(resumed, token),
+        other => panic!("expected session, got {:?}", other),
+    }
+    match connection.receive().unwrap() {
+        Message::Position {
+            position: resumed,
+            clock,
+        } => {
+            assert_eq!(resumed, position);
+            assert!(clock.unwrap() <= 30_000);
+        }
+        other => panic!("expected position, got {:?}", other),
+    }
+    connection
+        .send(&Message::Move {
+            movement: Some("b2".parse().unwrap()),
+        })
+        .unwrap();
+    play_until_over(&mut connection);
+
+    let record = game.join().unwrap();
+    assert_eq!(record.termination, None);
+    assert!(record.moves[0].movement.is_some());
+}
+
+#[test]
+fn forfeit_when_not_resuming_in_time() {
+    let (address, game) = server(Duration::from_millis(200), None);
+    let mut connection = connect(address);
+    while !matches!(connection.receive().unwrap(), Message::Position { .. }) {}
+    drop(connection);
+
+    let record = game.join().unwrap();
+    assert_eq!(
+        record.termination,
+        Some(Termination::Forfeit(false, ForfeitReason::Disconnection))
+    );
+}
+
+#[test]
+fn unknown_sessions_are_refused() {
+    let (address, game) = server(Duration::from_millis(500), None);
+    let mut connection = connect(address);
+    while !matches!(connection.receive().unwrap(), Message::Position { .. }) {}
+
+    let mut intruder = connect(address);
+    intruder
+        .send(&Message::Resume {
+            token: "not a token".to_owned(),
+        })
+        .unwrap();
+    assert!(matches!(intruder.receive().unwrap(), Message::Error { .. }));
+
+    // the real client is still playing
+    connection
+        .send(&Message::Move {
+            movement: Some("b2".parse().unwrap()),
+        })
+        .unwrap();
+    play_until_over(&mut connection);
+    assert_eq!(game.join().unwrap().termination, None);
+}
@@ -0,0 +1,36 @@
+//! Run with `cargo test --target wasm32-unknown-unknown --features wasm` and
+//! `wasm-bindgen-test-runner` as the target runner (node is enough, no browser needed).
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use blobwar::wasm::Game;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn play_standard_game() {
+    let mut game = Game::standard();
+    assert_eq!(game.to_move(), "red");
+    assert!(game.legal_moves().contains(&"b2".to_owned()));
+    game.play("b2").unwrap();
+    assert_eq!(game.position(), "r6b/1r6/8/8/8/8/8/b6r b 1");
+    assert!(game.play("c3").is_err());
+    assert_eq!(game.red_value(), 1);
+}
+
+#[wasm_bindgen_test]
+fn engines_play_until_the_end() {
+    let mut game = Game::new("rr6/rr6/8/8/8/8/6bb/6bb r 0").unwrap();
+    assert!(game.engine_move("alphabeta:time=100").is_err());
+    for _ in 0..200 {
+        if game.game_over() {
+            break;
+        }
+        let engine = if game.to_move() == "red" {
+            "alphabeta:depth=2"
+        } else {
+            "greedy"
+        };
+        let movement = game.engine_move(engine).unwrap();
+        game.play(&movement).unwrap();
+    }
+    assert!(game.game_over());
+}
@@ -0,0 +1,135 @@
+//! Web interface driven by a bare HTTP client, over loopback sockets.
+use blobwar::web::WebServer;
+
+use serde_json::{json, Value};
+
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+/// Start a web server in the background.
+fn server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || WebServer::default().serve_listener(listener));
+    address
+}
+
+/// Send a request, return the status code and the JSON answer.
+fn request(address: SocketAddr, method: &str, path: &str, body: &Value) -> (u16, Value) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    let body = body.to_string();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    let status = answer[9..12].parse().unwrap();
+    let (_, body) = answer.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+fn new_game(address: SocketAddr, red: &str, blue: &str) -> Value {
+    let (status, state) = request(
+        address,
+        "POST",
+        "/games",
+        &json!({"red": red, "blue": blue, "board": "r6b/8/8/8/8/8/8/b6r"}),
+    );
+    assert_eq!(status, 200, "{}", state);
+    state
+}
+
+fn play(address: SocketAddr, id: &Value, movement: &Value) -> (u16, Value) {
+    request(
+        address,
+        "POST",
+        &format!("/games/{}/move", id),
+        &json!({ "move": movement }),
+    )
+}
+
+#[test]
+fn create_a_game_and_play() {
+    let address = server();
+    let state = new_game(address, "human", "human");
+    assert_eq!(state["players"], json!(["human", "human"]));
+    assert_eq!(state["to_move"], "red");
+    assert_eq!(state["cells"].as_str().unwrap().len(), 64);
+    let id = &state["id"];
+
+    let (status, moves) = request(address, "GET", &format!("/games/{}/moves", id), &json!({}));
+    assert_eq!(status, 200);
+    assert_eq!(moves, state["legal_moves"]);
+
+    let movement = &state["legal_moves"][0];
+    let (status, state) = play(address, id, movement);
+    assert_eq!(status, 200, "{}", state);
+    assert_eq!(state["moves"], json!([movement]));
+    assert_eq!(state["to_move"], "blue");
+
+    let (status, state) = request(address, "GET", &format!("/games/{}", id), &json!({}));
+    assert_eq!(status, 200);
+    assert_eq!(state["moves"].as_array().unwrap().len(), 1);
+
+    let (status, _) = play(address, id, &json!("d4"));
+    assert_eq!(status, 400);
+    let (status, _) = request(address, "GET", "/games/1000", &json!({}));
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn only_search_engines_play_on_the_web() {
+    let address = server();
+    for spec in &[
+        "human:hint=greedy",
+        "tui",
+        "network:port=23456",
+        "process:command=ls",
+        "nothing",
+    ] {
+        let (status, answer) = request(
+            address,
+            "POST",
+            "/games",
+            &json!({ "red": spec, "blue": "human" }),
+        );
+        assert_eq!(status, 400, "{} accepted", spec);
+        assert!(answer["error"].is_string());
+    }
+    let state = new_game(address, "human", "alphabeta:depth=2");
+    assert_eq!(state["players"], json!(["human", "alphabeta:depth=2"]));
+}
+
+#[test]
+fn events_follow_the_game() {
+    let address = server();
+    let state = new_game(address, "human", "greedy");
+    let id = &state["id"];
+
+    let mut events = TcpStream::connect(address).unwrap();
+    write!(events, "GET /games/{}/events HTTP/1.1\r\n\r\n", id).unwrap();
+    let mut events = BufReader::new(events).lines().map(|line| line.unwrap());
+    assert!(events.next().unwrap().contains("200 OK"));
+    let mut states = events.filter_map(|line| {
+        line.strip_prefix("data: ")
+            .map(|data| serde_json::from_str::<Value>(data).unwrap())
+    });
+    assert_eq!(states.next().unwrap()["moves"], json!([]));
+
+    let (status, _) = play(address, id, &state["legal_moves"][0]);
+    assert_eq!(status, 200);
+
+    let after_ours = states.next().unwrap();
+    assert_eq!(after_ours["moves"], json!([state["legal_moves"][0]]));
+    let after_greedy = states.next().unwrap();
+    assert_eq!(after_greedy["moves"].as_array().unwrap().len(), 2);
+    assert_eq!(after_greedy["to_move"], "red");
+}